@@ -0,0 +1,50 @@
+// `get_tbl_id` throughput, run with `cargo bench`. The binary has no lib
+// target so the formatting module is included by path.
+#[path = "../src/tbl_id.rs"]
+mod tbl_id;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+use surrealdb::sql::{Id, Thing};
+
+fn things() -> Vec<Thing> {
+    (0..1000)
+        .map(|n| match n % 2 {
+            0 => Thing::from(("building_tbl", Id::Number(1234567890 + n))),
+            _ => Thing::from(("building_tbl", format!("{n}").as_str())),
+        })
+        .collect()
+}
+
+fn bench_tbl_id(c: &mut Criterion) {
+    let things = things();
+
+    // The output has to stay byte-identical to SurrealDB's own formatting
+    let mut buf = String::new();
+    for thing in &things {
+        buf.clear();
+        tbl_id::write_tbl_id(&mut buf, thing);
+        assert_eq!(buf, thing.to_raw());
+    }
+
+    c.bench_function("to_raw", |b| {
+        b.iter(|| {
+            for thing in &things {
+                black_box(black_box(thing).to_raw());
+            }
+        })
+    });
+    c.bench_function("write_tbl_id", |b| {
+        let mut buf = String::new();
+        b.iter(|| {
+            for thing in &things {
+                buf.clear();
+                tbl_id::write_tbl_id(&mut buf, black_box(thing));
+                black_box(&buf);
+            }
+        })
+    });
+}
+
+criterion_group!(benches, bench_tbl_id);
+criterion_main!(benches);
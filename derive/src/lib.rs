@@ -0,0 +1,94 @@
+// `#[derive(SurrealRecord)]` for structs with an `id: Thing` field. It
+// generates inherent methods forwarding to `IdTraits` on `id` and the
+// `meta::id` projection query, so a record type doesn't need its own `rid`
+// boilerplate. The generated code names `crate::IdTraits`, from any other
+// crate say where the trait lives with `#[surreal(crate = path)]`, the
+// path of the module exporting `IdTraits`.
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Type};
+
+#[proc_macro_derive(SurrealRecord, attributes(surreal))]
+pub fn derive_surreal_record(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut krate: syn::Path = syn::parse_quote!(crate);
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("surreal")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("crate") {
+                krate = meta.value()?.parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("expected `crate = path`"))
+            }
+        })?;
+    }
+
+    let id = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .find(|f| f.ident.as_ref().is_some_and(|i| i == "id")),
+            _ => None,
+        },
+        _ => None,
+    };
+    let Some(id) = id else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "SurrealRecord needs a struct with an `id: Thing` field",
+        ));
+    };
+    // Checked by name, so `Thing` and `surrealdb::sql::Thing` pass but an
+    // alias doesn't
+    let is_thing = match &id.ty {
+        Type::Path(ty) => {
+            ty.qself.is_none()
+                && ty
+                    .path
+                    .segments
+                    .last()
+                    .is_some_and(|s| s.ident == "Thing" && s.arguments.is_none())
+        }
+        _ => false,
+    };
+    if !is_thing {
+        return Err(syn::Error::new_spanned(
+            &id.ty,
+            "SurrealRecord needs `id` to be a `Thing`",
+        ));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            // `get_tbl_id()` of the record's id
+            pub fn tbl_id(&self) -> String {
+                #krate::IdTraits::get_tbl_id(&self.id)
+            }
+
+            // `get_id()` of the record's id, unescaped
+            pub fn rid(&self) -> String {
+                #krate::IdTraits::get_id(&self.id)
+            }
+
+            pub fn tbl(&self) -> String {
+                #krate::IdTraits::get_tbl(&self.id)
+            }
+
+            // Select every row of the table bound to `$tbl` with the
+            // unescaped id projected as `rid`
+            pub fn rid_projection() -> &'static str {
+                "SELECT *, meta::id(id) AS rid FROM type::table($tbl)"
+            }
+        }
+    })
+}
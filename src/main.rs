@@ -1,13 +1,219 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use exper_surrealdb_getting_record_id_derive::SurrealRecord;
+use futures::{future, Stream, StreamExt};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
+use std::fmt;
+use std::future::IntoFuture;
+use std::io::Write;
+use std::ops::Bound;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use surrealdb::engine::local::{Db, Mem};
-use surrealdb::sql::Thing;
-use surrealdb::Surreal;
+use surrealdb::sql::{Datetime, Id, Range, Thing};
+use surrealdb::{method, Action, Connection, Notification, Response, Surreal};
+
+mod tbl_id;
+pub use tbl_id::write_tbl_id;
+
+// `use crate::prelude::*;` is the intended entry point, it brings in the
+// traits, record structs, helpers and the SurrealDB types they're used with.
+pub mod prelude {
+    pub use crate::{
+        connect_mem_named, select_all_flat, select_fill_rid, AddressCount, Building,
+        BuildingIsMain, BuildingWithDeletedAt, BuildingWithOwner, BuildingWithParent,
+        BuildingWithRidOptionString, BuildingWithRidString, BuildingWithThing, CachedStore,
+        CountResult, IdTraits, Owner, ProtoRecordId, Record, RecordId, RecordIdError, RecordMeta,
+        SortableThing,
+    };
+    pub use surrealdb::engine::local::{Db, Mem};
+    pub use surrealdb::sql::Thing;
+    pub use surrealdb::Surreal;
+}
+
+#[derive(Debug)]
+pub enum RecordIdError {
+    // The operation didn't complete within the allotted time
+    Timeout(Duration),
+    // A `tbl:id` string that SurrealDB couldn't parse
+    Parse(String),
+    // The record is in a different table than the one expected
+    WrongTable {
+        expected: String,
+        found: String,
+    },
+    // A field name that isn't a plain identifier
+    InvalidField(String),
+    // A table name SurrealDB would need escaped, passed unescaped
+    InvalidTable(String),
+    // The query produced a different number of statement results
+    SlotCountMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    // Row `index` of a select didn't deserialize, `field` is the missing
+    // field when that was the cause and `raw` the row as JSON
+    DeserializeRow {
+        index: usize,
+        field: Option<String>,
+        raw: serde_json::Value,
+    },
+    // The table has more rows than a select was allowed to load
+    TooManyRows {
+        count: usize,
+        limit: usize,
+    },
+    // There's no record with this id
+    NotFound(Thing),
+    // The table isn't defined in the database
+    UnknownTable {
+        table: String,
+    },
+    // The engine can't read records as they were at an earlier time
+    VersioningUnsupported,
+    Db(Box<surrealdb::Error>),
+}
+
+impl fmt::Display for RecordIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordIdError::Timeout(dur) => write!(f, "timed out after {dur:?}"),
+            RecordIdError::Parse(msg) => write!(f, "invalid record id: {msg}"),
+            RecordIdError::WrongTable { expected, found } => {
+                write!(f, "expected a record in table {expected}, found {found}")
+            }
+            RecordIdError::InvalidField(field) => write!(f, "invalid field name {field:?}"),
+            RecordIdError::InvalidTable(table) => write!(f, "invalid table name {table:?}"),
+            RecordIdError::SlotCountMismatch { expected, actual } => {
+                write!(f, "expected {expected} statement results, got {actual}")
+            }
+            RecordIdError::DeserializeRow { index, field, raw } => match field {
+                Some(field) => write!(f, "row {index} is missing field `{field}`: {raw}"),
+                None => write!(f, "row {index} doesn't deserialize: {raw}"),
+            },
+            RecordIdError::TooManyRows { count, limit } => {
+                write!(f, "table has {count} rows, more than the limit of {limit}")
+            }
+            RecordIdError::NotFound(thing) => write!(f, "no record {thing}"),
+            RecordIdError::UnknownTable { table } => write!(f, "no table named {table}"),
+            RecordIdError::VersioningUnsupported => {
+                write!(f, "the database engine doesn't keep record versions")
+            }
+            RecordIdError::Db(e) => write!(f, "database error: {e}"),
+        }
+    }
+}
+
+impl Error for RecordIdError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RecordIdError::Db(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<surrealdb::Error> for RecordIdError {
+    fn from(e: surrealdb::Error) -> Self {
+        RecordIdError::Db(Box::new(e))
+    }
+}
 
 pub trait IdTraits {
     fn get_tbl_id(&self) -> String;
     fn get_id(&self) -> String;
     fn get_tbl(&self) -> String;
+
+    // Owned `(table, id)` pair with the id unescaped. Prefer this to
+    // splitting `get_tbl_id()` on `:` as string ids may contain colons.
+    // (A `From<&Thing> for (String, String)` impl isn't allowed by the
+    // orphan rule, both types are foreign.)
+    fn get_tbl_id_tuple(&self) -> (String, String) {
+        (self.get_tbl(), self.get_id())
+    }
+
+    // `get_tbl_id()` with caller-chosen delimiters, e.g. `("", "", "/")`
+    // for `building_tbl/1234567890` keys. `open` and `close` replace the
+    // `⟨` and `⟩` only where SurrealDB would escape. This default escapes
+    // `get_tbl()` and, unless `is_numeric_id()`, `get_id()` the way the
+    // `Thing` impl does, it can't tell array or object ids from strings.
+    fn get_tbl_id_with(&self, open: &str, close: &str, sep: &str) -> String {
+        let mut buf = String::new();
+        tbl_id::write_part(&mut buf, &self.get_tbl(), open, close);
+        buf.push_str(sep);
+        let id = self.get_id();
+        if self.is_numeric_id() {
+            buf.push_str(&id);
+        } else {
+            tbl_id::write_part(&mut buf, &id, open, close);
+        }
+        buf
+    }
+
+    // The numeric bounds of a range id such as `building_tbl:1..100`, None
+    // when `self` isn't a range or a bound isn't a number. SurrealDB 1.x
+    // keeps ranges in `sql::Range` rather than as a `Thing` id variant, so
+    // only the `Range` impl returns Some.
+    fn get_id_range(&self) -> Option<(Bound<i64>, Bound<i64>)> {
+        None
+    }
+
+    // The id as a number, parsed from `get_id()` so digit strings like the
+    // demo's "1234567890" count. None rather than a truncated value when it
+    // doesn't fit, e.g. `9223372036854775808` (i64::MAX + 1).
+    fn get_id_as_i64(&self) -> Option<i64> {
+        self.get_id().parse().ok()
+    }
+
+    fn get_id_as_i128(&self) -> Option<i128> {
+        self.get_id().parse().ok()
+    }
+
+    // Also accepts fractional ids, up to the 28 digits a `Decimal` holds
+    fn get_id_as_decimal(&self) -> Option<Decimal> {
+        self.get_id().parse().ok()
+    }
+
+    // The id as a timestamp when it's an RFC 3339 datetime, such as the
+    // `⟨2024-01-01T00:00:00Z⟩` string id SurrealDB 1.x stores for one (it
+    // has no datetime `Id` variant).
+    #[cfg(feature = "chrono")]
+    fn get_id_as_datetime(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        chrono::DateTime::parse_from_rfc3339(&self.get_id())
+            .ok()
+            .map(|dt| dt.with_timezone(&chrono::Utc))
+    }
+
+    // True for an `Id::Number` id, a numeric looking string id is not
+    fn is_numeric_id(&self) -> bool {
+        false
+    }
+
+    // `get_id()` without the allocation when the id is already a string
+    fn get_id_cow(&self) -> Cow<'_, str> {
+        Cow::Owned(self.get_id())
+    }
+
+    // A `type::thing('building_tbl', '1234567890')` call naming this record,
+    // for splicing into generated SurrealQL. The table and string ids are
+    // quoted string literals so they can't end the expression early.
+    fn get_type_thing_expr(&self) -> String {
+        let tb = surrealdb::sql::Strand::from(self.get_tbl());
+        if self.is_numeric_id() {
+            format!("type::thing({tb}, {})", self.get_id())
+        } else {
+            let id = surrealdb::sql::Strand::from(self.get_id());
+            format!("type::thing({tb}, {id})")
+        }
+    }
 }
 
 impl IdTraits for Thing {
@@ -15,7 +221,7 @@ impl IdTraits for Thing {
     // is a `Thing` and if the `thing.id` field is Decimal Number than those
     // characters surround the id. And those aren't the '<' and '>' characters!
     fn get_tbl_id(&self) -> String {
-        self.to_raw()
+        self.get_tbl_id_with("⟨", "⟩", ":")
 
         // This will not have the surrounding `⟨` and `⟩` characters on Numbers
         //self.get_tbl() + ":" + &self.get_id()
@@ -28,142 +234,5212 @@ impl IdTraits for Thing {
     fn get_tbl(&self) -> String {
         self.tb.to_string()
     }
+
+    // Same escaping as `to_raw()` except that a part containing `⟩` is
+    // quoted in backticks when `close` is `⟩`, see `tbl_id::write_tbl_id_with`.
+    // That keeps `get_tbl_id(parse_tbl_id(get_tbl_id(t))) == get_tbl_id(t)`.
+    fn get_tbl_id_with(&self, open: &str, close: &str, sep: &str) -> String {
+        let mut buf = String::with_capacity(self.tb.len() + sep.len() + 16);
+        tbl_id::write_tbl_id_with(&mut buf, self, open, close, sep);
+        buf
+    }
+
+    fn is_numeric_id(&self) -> bool {
+        matches!(self.id, Id::Number(_))
+    }
+
+    // Array and object ids are written as their SurrealQL literals
+    fn get_type_thing_expr(&self) -> String {
+        let tb = surrealdb::sql::Strand::from(self.tb.as_str());
+        match &self.id {
+            Id::String(s) => {
+                let id = surrealdb::sql::Strand::from(s.as_str());
+                format!("type::thing({tb}, {id})")
+            }
+            id => format!("type::thing({tb}, {id})"),
+        }
+    }
+
+    // Borrows `Id::String`, numbers and the other variants have to be
+    // formatted so they're owned.
+    fn get_id_cow(&self) -> Cow<'_, str> {
+        match &self.id {
+            Id::String(s) => Cow::Borrowed(s),
+            id => Cow::Owned(id.to_raw()),
+        }
+    }
+}
+
+// Raw query output, e.g. a `take` into `Value`. Only a `Value::Thing` has
+// an id, any other value gives empty strings.
+impl IdTraits for surrealdb::sql::Value {
+    fn get_tbl_id(&self) -> String {
+        match self {
+            surrealdb::sql::Value::Thing(thing) => thing.get_tbl_id(),
+            _ => String::new(),
+        }
+    }
+
+    fn get_id(&self) -> String {
+        match self {
+            surrealdb::sql::Value::Thing(thing) => thing.get_id(),
+            _ => String::new(),
+        }
+    }
+
+    fn get_tbl(&self) -> String {
+        match self {
+            surrealdb::sql::Value::Thing(thing) => thing.get_tbl(),
+            _ => String::new(),
+        }
+    }
+
+    fn get_tbl_id_with(&self, open: &str, close: &str, sep: &str) -> String {
+        match self {
+            surrealdb::sql::Value::Thing(thing) => thing.get_tbl_id_with(open, close, sep),
+            _ => String::new(),
+        }
+    }
+
+    fn is_numeric_id(&self) -> bool {
+        matches!(self, surrealdb::sql::Value::Thing(thing) if thing.is_numeric_id())
+    }
+}
+
+// Lets a borrowed `&Thing` (or `&Range`) be passed where a `T: IdTraits`
+// is taken by value, without cloning. Every method delegates so overridden
+// defaults such as `get_id_range` are kept.
+impl<T: IdTraits + ?Sized> IdTraits for &T {
+    fn get_tbl_id(&self) -> String {
+        (**self).get_tbl_id()
+    }
+
+    fn get_id(&self) -> String {
+        (**self).get_id()
+    }
+
+    fn get_tbl(&self) -> String {
+        (**self).get_tbl()
+    }
+
+    fn get_tbl_id_with(&self, open: &str, close: &str, sep: &str) -> String {
+        (**self).get_tbl_id_with(open, close, sep)
+    }
+
+    fn get_tbl_id_tuple(&self) -> (String, String) {
+        (**self).get_tbl_id_tuple()
+    }
+
+    fn get_id_range(&self) -> Option<(Bound<i64>, Bound<i64>)> {
+        (**self).get_id_range()
+    }
+
+    fn get_id_as_i64(&self) -> Option<i64> {
+        (**self).get_id_as_i64()
+    }
+
+    fn get_id_as_i128(&self) -> Option<i128> {
+        (**self).get_id_as_i128()
+    }
+
+    fn get_id_as_decimal(&self) -> Option<Decimal> {
+        (**self).get_id_as_decimal()
+    }
+
+    #[cfg(feature = "chrono")]
+    fn get_id_as_datetime(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        (**self).get_id_as_datetime()
+    }
+
+    fn is_numeric_id(&self) -> bool {
+        (**self).is_numeric_id()
+    }
+
+    fn get_id_cow(&self) -> Cow<'_, str> {
+        (**self).get_id_cow()
+    }
+
+    fn get_type_thing_expr(&self) -> String {
+        (**self).get_type_thing_expr()
+    }
+}
+
+// The bounds follow SurrealQL: `1..100` includes 1 and excludes 100,
+// `1..=100` includes both ends, `1>..100` excludes both and an empty side
+// such as `..100` is unbounded.
+impl IdTraits for Range {
+    fn get_tbl_id(&self) -> String {
+        self.to_string()
+    }
+
+    // Everything after `tb:`, e.g. `1..100`
+    fn get_id(&self) -> String {
+        self.to_string()[self.tb.len() + 1..].to_owned()
+    }
+
+    fn get_tbl(&self) -> String {
+        self.tb.clone()
+    }
+
+    // Only the table can need escaping, the id is range syntax
+    fn get_tbl_id_with(&self, open: &str, close: &str, sep: &str) -> String {
+        let mut buf = String::new();
+        tbl_id::write_part(&mut buf, &self.tb, open, close);
+        buf.push_str(sep);
+        buf.push_str(&self.get_id());
+        buf
+    }
+
+    fn get_id_range(&self) -> Option<(Bound<i64>, Bound<i64>)> {
+        fn numeric(bound: &Bound<Id>) -> Option<Bound<i64>> {
+            match bound {
+                Bound::Included(Id::Number(n)) => Some(Bound::Included(*n)),
+                Bound::Excluded(Id::Number(n)) => Some(Bound::Excluded(*n)),
+                Bound::Unbounded => Some(Bound::Unbounded),
+                _ => None,
+            }
+        }
+        Some((numeric(&self.beg)?, numeric(&self.end)?))
+    }
+}
+
+// Parse a `get_tbl_id()` string back into a `Thing` using SurrealDB's own
+// parser, so the `⟨` and `⟩` escaping is understood.
+pub fn parse_tbl_id(s: &str) -> Result<Thing, RecordIdError> {
+    surrealdb::sql::thing(s).map_err(|e| RecordIdError::Parse(e.to_string()))
+}
+
+// `parse_tbl_id` through `sql::value`, SurrealDB's parser for a whole
+// SurrealQL value, for checking that a string is a record id SurrealQL
+// itself accepts. Besides what `parse_tbl_id` takes (plain, `⟨..⟩` and
+// backtick escaped parts, array and object ids) it accepts the record
+// string forms `r'tbl:id'` and, in SurrealDB 1.x, a quoted `'tbl:id'`.
+// Like `parse_tbl_id` it rejects surrounding whitespace and `\⟩` inside
+// `⟨..⟩`. Anything parsing to another kind of value, such as a bare
+// number or identifier, is a `RecordIdError::Parse`.
+pub fn thing_via_sql_parse(s: &str) -> Result<Thing, RecordIdError> {
+    match surrealdb::sql::value(s).map_err(|e| RecordIdError::Parse(e.to_string()))? {
+        surrealdb::sql::Value::Thing(thing) => Ok(thing),
+        v => Err(RecordIdError::Parse(format!(
+            "{s:?} is not a record id but {v}"
+        ))),
+    }
+}
+
+// `raw` with each `⟨` and `⟩` written as `[U+27E8]` and `[U+27E9]`, for
+// logs where they'd pass for `<` and `>`. The ASCII characters are left
+// alone so the two can be told apart.
+pub fn annotate_escapes(raw: &str) -> String {
+    raw.replace('⟨', "[U+27E8]").replace('⟩', "[U+27E9]")
+}
+
+// `(to_raw, to_string)` of `t`, for checking how SurrealDB formats a
+// `Thing`. In SurrealDB 1.x `to_raw` is just `to_string`, both escape the
+// same way, and `get_tbl_id` matches them except for ids containing `⟩`
+// (see `get_tbl_id_with`).
+pub fn compare_thing_formats(t: &Thing) -> (String, String) {
+    (t.to_raw(), t.to_string())
+}
+
+// A compact binary key for caches such as Redis. Serde keeps the `Id`
+// variant, so a numeric id stays a number through `thing_from_msgpack`.
+pub fn thing_to_msgpack(t: &Thing) -> Vec<u8> {
+    // Encoding into a Vec can't fail for a `Thing`, only writing can
+    rmp_serde::to_vec(t).expect("a Thing always encodes as MessagePack")
+}
+
+pub fn thing_from_msgpack(bytes: &[u8]) -> Result<Thing, RecordIdError> {
+    rmp_serde::from_slice(bytes).map_err(|e| RecordIdError::Parse(e.to_string()))
+}
+
+// An opaque pagination cursor, the MessagePack `Thing` base64url encoded
+// without padding so it can go straight into a query string.
+pub fn thing_to_cursor(t: &Thing) -> String {
+    URL_SAFE_NO_PAD.encode(thing_to_msgpack(t))
+}
+
+pub fn thing_from_cursor(cursor: &str) -> Result<Thing, RecordIdError> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(cursor)
+        .map_err(|e| RecordIdError::Parse(e.to_string()))?;
+    thing_from_msgpack(&bytes)
+}
+
+// A `Thing` whose table follows SurrealDB's identifier rules: ASCII
+// letters, digits and `_`, not starting with a digit. Any other name has
+// to be escaped, `⟨1st floor⟩` or `` `1st floor` ``, and the escaping is
+// stripped from the returned table.
+pub fn thing_checked(table: &str, id: Id) -> Result<Thing, RecordIdError> {
+    let invalid = || RecordIdError::InvalidTable(table.to_owned());
+    if !table.starts_with(['⟨', '`']) {
+        validate_field(table).map_err(|_| invalid())?;
+        return Ok(Thing::from((table, id)));
+    }
+    match unescape_part(table, None) {
+        Ok((tb, "")) if !tb.is_empty() => Ok(Thing::from((tb.as_ref(), id))),
+        _ => Err(invalid()),
+    }
+}
+
+// An owned copy of `t`, nothing is shared with the original so either may
+// be changed or dropped independently. A plain `Clone`, there's no need
+// to serialize a `Thing` to copy it.
+pub fn clone_thing(t: &Thing) -> Thing {
+    t.clone()
+}
+
+// Two Things are equal when both the table and the typed id match, so
+// `Id::Number(1)` and `Id::String("1")` are different records. Object ids
+// compare by field regardless of the order they were written in, an
+// `Object` is a `BTreeMap` so the fields are already kept sorted.
+pub fn things_equal(a: &Thing, b: &Thing) -> bool {
+    a.tb == b.tb && a.id == b.id
+}
+
+// `things_equal` ignoring the case of the table name, the id still has to
+// match exactly. SurrealDB tables are case sensitive, `Building_Tbl` and
+// `building_tbl` are different tables, so this is only for comparing ids
+// from sources that don't preserve case, such as a case-insensitive URL
+// or config key, not for deciding whether two ids name the same record.
+pub fn things_equal_ci(a: &Thing, b: &Thing) -> bool {
+    a.tb.to_lowercase() == b.tb.to_lowercase() && a.id == b.id
+}
+
+// `assert_thing_id!(thing, ("building_tbl", "1234567890"))` checks that
+// `thing` has that table and unescaped id, so the numeric and string forms
+// of an id both match. On failure both sides are shown escaped, as
+// `get_tbl_id()` gives them, and unescaped.
+#[macro_export]
+macro_rules! assert_thing_id {
+    ($thing:expr, ($table:expr, $id:expr) $(,)?) => {{
+        let thing = &$thing;
+        let (table, id): (&str, &str) = ($table, $id);
+        let (found_tbl, found_id) = $crate::IdTraits::get_tbl_id_tuple(thing);
+        if found_tbl != table || found_id != id {
+            let expected = $crate::prelude::Thing::from((table, id));
+            panic!(
+                concat!(
+                    "assertion `thing id` failed\n",
+                    "     found: {} (unescaped {}:{})\n",
+                    "  expected: {} (unescaped {}:{})",
+                ),
+                $crate::IdTraits::get_tbl_id(thing),
+                found_tbl,
+                found_id,
+                $crate::IdTraits::get_tbl_id(&expected),
+                table,
+                id,
+            );
+        }
+    }};
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-struct BuildingWithThing {
-    id: Thing,
-    address: String,
+pub struct BuildingWithThing {
+    pub id: Thing,
+    pub address: String,
 }
 
+// A building linked to another one. A record link deserializes into a
+// `Thing` wherever it's stored, so `parent` works like `id` with both
+// `db.select` and the query helpers. None when the field isn't set.
 #[derive(Serialize, Deserialize, Debug)]
-struct BuildingWithRidString {
-    rid: String,
-    address: String,
+pub struct BuildingWithParent {
+    pub id: Thing,
+    pub address: String,
+    pub parent: Option<Thing>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct BuildingWithRidString {
+    /// The unescaped record id, as returned by `meta::id(id)`
+    pub rid: String,
+    pub address: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-struct BuildingWithRidOptionString {
-    rid: Option<String>,
-    address: String,
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct BuildingWithRidOptionString {
+    /// The unescaped record id, as returned by `meta::id(id)`
+    pub rid: Option<String>,
+    pub address: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
-struct Building {
-    address: String,
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Building {
+    // Older rows stored the address as `addr`. A row with both fields is a
+    // duplicate field error rather than picking one.
+    #[serde(alias = "addr")]
+    pub address: String,
 }
 
-#[allow(unused)]
-async fn test_select(db: &Surreal<Db>, address: &str) -> Result<(), Box<dyn Error>> {
-    // Using `db.select` always returns None for `rid: Option<String>` field
-    let select_results_with_rid_option_string: Vec<BuildingWithRidOptionString> =
-        db.select("building_tbl").await?;
-    dbg!(&select_results_with_rid_option_string);
-    assert_eq!(select_results_with_rid_option_string.len(), 1);
-    assert_eq!(select_results_with_rid_option_string[0].rid, None);
-    assert_eq!(select_results_with_rid_option_string[0].address, address);
+// Start a query with `$tbl` bound to `table`. Queries select from
+// `type::table($tbl)` rather than interpolating the name, so a table name
+// from user input is always a literal table and can't inject SurrealQL.
+pub fn bound_table<'r>(db: &'r Surreal<Db>, sql: &str, table: &str) -> method::Query<'r, Db> {
+    db.query(sql.to_owned()).bind(("tbl", table.to_owned()))
+}
 
-    // Using `db.select` with no `rid` works too
-    let select_results_no_rid: Vec<Building> = db.select("building_tbl").await?;
-    dbg!(&select_results_no_rid);
-    assert_eq!(select_results_no_rid.len(), 1);
-    assert_eq!(select_results_no_rid[0].address, address);
+// Select all records of `table` with `meta::id(id) AS rid` projected so
+// the non-optional `rid: String` field is always present. This is the
+// ergonomic default, `db.select` can't populate `rid` at all. Records
+// marked with `soft_delete` are left out.
+pub async fn select_all_flat(
+    db: &Surreal<Db>,
+    table: &str,
+) -> Result<Vec<BuildingWithRidString>, Box<dyn Error>> {
+    select_all_flat_with(db, table, false).await
+}
 
-    // Having `rid: String` always fails when using select.
-    match db.select("building_tbl").await {
-        Ok(r) => {
-            let _: Vec<BuildingWithRidString> = r;
-            panic!("Should have failed");
-        }
-        Err(e) => {
-            //dbg!(&e);
-            assert!(e.to_string().contains("missing field"));
-        }
+// `select_all_flat` for large tables, with `PARALLEL` so SurrealDB may
+// scan in parallel. The rows are the same, only their order may differ.
+pub async fn select_all_parallel(
+    db: &Surreal<Db>,
+    table: &str,
+) -> Result<Vec<BuildingWithRidString>, Box<dyn Error>> {
+    select_all_flat_with(db, table, true).await
+}
+
+// `select_all_flat`, or `select_all_parallel` when `parallel` is set
+pub async fn select_all_flat_with(
+    db: &Surreal<Db>,
+    table: &str,
+    parallel: bool,
+) -> Result<Vec<BuildingWithRidString>, Box<dyn Error>> {
+    let mut sql = String::from(
+        "SELECT *, meta::id(id) AS rid FROM type::table($tbl) WHERE deleted_at = NONE",
+    );
+    if parallel {
+        sql.push_str(" PARALLEL");
     }
+    let mut response = bound_table(db, &sql, table).await?;
+    let results: Vec<BuildingWithRidString> = response.take(0)?;
+    Ok(results)
+}
 
+// `select_all_flat` for any `T` into the caller's `out`, which is cleared
+// first. A polling loop can keep one Vec and reuse its capacity, though
+// the response still deserializes into a Vec of its own.
+pub async fn select_all_into<T: DeserializeOwned>(
+    db: &Surreal<Db>,
+    table: &str,
+    out: &mut Vec<T>,
+) -> Result<(), Box<dyn Error>> {
+    let mut response = bound_table(
+        db,
+        "SELECT *, meta::id(id) AS rid FROM type::table($tbl) WHERE deleted_at = NONE",
+        table,
+    )
+    .await?;
+    let results: Vec<T> = response.take(0)?;
+    out.clear();
+    out.extend(results);
     Ok(())
 }
 
-#[allow(unused)]
-async fn test_query(db: &Surreal<Db>, address: &str, rid: &str) -> Result<(), Box<dyn Error>> {
-    // Query all fields without the meta::id as rid
-    let mut query_response_no_rid = db.query(r#"SELECT * FROM building_tbl"#).await?;
-    dbg!(&query_response_no_rid);
-    let query_results_no_rid: Vec<BuildingWithRidOptionString> = query_response_no_rid.take(0)?;
-    dbg!(&query_results_no_rid);
-    assert_eq!(query_results_no_rid.len(), 1);
-    assert_eq!(query_results_no_rid[0].rid, None);
-    assert_eq!(query_results_no_rid[0].address, address);
+// A building with a computed `is_main` column next to the `rid` metadata
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BuildingIsMain {
+    pub rid: String,
+    pub address: String,
+    pub is_main: bool,
+}
 
-    // Query all fields and the meta::id as rid
-    let mut query_response_with_rid = db
-        .query(r#"SELECT *,meta::id(id) AS rid FROM building_tbl"#)
+// `building_tbl` with a boolean computed per row, whether the address is
+// on a Main street. `string::contains` is case sensitive.
+pub async fn select_is_main_street(
+    db: &Surreal<Db>,
+) -> Result<Vec<BuildingIsMain>, Box<dyn Error>> {
+    let mut response = db
+        .query(
+            "SELECT *, meta::id(id) AS rid, string::contains(address, 'Main') AS is_main \
+             FROM building_tbl",
+        )
         .await?;
-    dbg!(&query_response_with_rid);
-    let query_results_with_rid: Vec<BuildingWithRidOptionString> =
-        query_response_with_rid.take(0)?;
-    dbg!(&query_results_with_rid);
-    assert_eq!(query_results_with_rid.len(), 1);
-    assert_eq!(query_results_with_rid[0].rid, Some(rid.to_owned()));
-    assert_eq!(query_results_with_rid[0].address, address);
+    Ok(response.take(0)?)
+}
 
-    // Query without `meta::id as rid` and no field rid: Option<String>
-    let mut query_response_no_rid_in_query_or_struct =
-        db.query(r#"SELECT * FROM building_tbl"#).await?;
-    dbg!(&query_response_no_rid_in_query_or_struct);
-    let query_results_no_rid_in_query_or_struct: Vec<Building> =
-        query_response_no_rid_in_query_or_struct.take(0)?;
-    dbg!(&query_results_no_rid_in_query_or_struct);
-    assert_eq!(query_results_no_rid_in_query_or_struct.len(), 1);
-    assert_eq!(query_results_no_rid_in_query_or_struct[0].address, address);
+// `select_all_flat` for any `T` as an `Arc<[T]>`, so a result shared by
+// many tasks is cloned by reference count rather than copied.
+pub async fn select_all_arc<T: DeserializeOwned>(
+    db: &Surreal<Db>,
+    table: &str,
+) -> Result<Arc<[T]>, Box<dyn Error>> {
+    let mut response = bound_table(
+        db,
+        "SELECT *, meta::id(id) AS rid FROM type::table($tbl) WHERE deleted_at = NONE",
+        table,
+    )
+    .await?;
+    let results: Vec<T> = response.take(0)?;
+    Ok(results.into())
+}
 
-    // Query with `meta::id(id) as rid` but no field `rid: Option<String>`
-    let mut query_response_rid_in_query_no_rid_in_struct = db
-        .query(r#"SELECT *,meta::id(id) AS rid FROM building_tbl"#)
+// A building with its soft-delete marker, None while it's live
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BuildingWithDeletedAt {
+    pub rid: String,
+    pub address: String,
+    pub deleted_at: Option<Datetime>,
+}
+
+// The raw view of `table`, soft-deleted records included
+pub async fn select_all_including_deleted(
+    db: &Surreal<Db>,
+    table: &str,
+) -> Result<Vec<BuildingWithDeletedAt>, Box<dyn Error>> {
+    let mut response = bound_table(
+        db,
+        "SELECT *, meta::id(id) AS rid FROM type::table($tbl)",
+        table,
+    )
+    .await?;
+    let results: Vec<BuildingWithDeletedAt> = response.take(0)?;
+    Ok(results)
+}
+
+// Mark `thing` deleted by setting `deleted_at` rather than removing it,
+// returning false if there's no such record.
+pub async fn soft_delete(db: &Surreal<Db>, thing: &Thing) -> Result<bool, Box<dyn Error>> {
+    // Without `WHERE id` an UPDATE of a missing record would create it
+    let mut response = db
+        .query("UPDATE $thing SET deleted_at = time::now() WHERE id")
+        .bind(("thing", thing.clone()))
         .await?;
-    dbg!(&query_response_rid_in_query_no_rid_in_struct);
-    let query_results_rid_in_query_no_rid_in_struct: Vec<Building> =
-        query_response_rid_in_query_no_rid_in_struct.take(0)?;
-    dbg!(&query_results_rid_in_query_no_rid_in_struct);
-    assert_eq!(query_results_rid_in_query_no_rid_in_struct.len(), 1);
-    assert_eq!(
-        query_results_rid_in_query_no_rid_in_struct[0].address,
-        address
-    );
+    let updated: Vec<BuildingWithThing> = response.take(0)?;
+    Ok(!updated.is_empty())
+}
 
-    Ok(())
+// The `{ count: N }` row returned by `SELECT count() FROM t GROUP ALL`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CountResult {
+    pub count: usize,
 }
 
-async fn test_select_thing_with_id_traits(db: &Surreal<Db>, address: &str, tbl: &str, id: &str) -> Result<(), Box<dyn Error>> {
-    let select_results_with_id_traits: Vec<BuildingWithThing> = db.select("building_tbl").await?;
-    dbg!(&select_results_with_id_traits);
-    assert_eq!(select_results_with_id_traits.len(), 1);
-    assert_eq!(&select_results_with_id_traits[0].id.get_tbl(), tbl);
-    assert_eq!(&select_results_with_id_traits[0].id.get_id(), id);
-    // Note the `⟨` and `⟩` in the `id` field. This is because the `id` field is a `Thing` and the `id` field is a `meta::id` field.
-    // and those aren't the '<' and '>' characters!
-    assert_eq!(&select_results_with_id_traits[0].id.get_tbl_id(), &(tbl.to_owned() + ":⟨" + id + "⟩"));
-    assert_eq!(&select_results_with_id_traits[0].address, address);
+// One row of `SELECT address, count() AS n ... GROUP BY address`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AddressCount {
+    pub address: String,
+    pub n: usize,
+}
 
-    println!("id: {:?}", select_results_with_id_traits[0].id);
-    println!("id.id: {:?}", select_results_with_id_traits[0].id.id);
-    let id_get_tbl_id = select_results_with_id_traits[0].id.get_tbl_id();
-    println!("id_get_tbl_id: {}", id_get_tbl_id);
-    let id_get_id = select_results_with_id_traits[0].id.get_id();
-    println!("id_get_id: {}", id_get_id);
-    let id_get_tbl = select_results_with_id_traits[0].id.get_tbl();
-    println!("id_get_tbl: {}", id_get_tbl);
+// The key metadata of a record, from `select_with_meta`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordMeta {
+    pub rid: String,
+    pub tbl: String,
+}
 
-    Ok(())
+// `meta::id` and `meta::tb` of every record in `table`. SurrealDB 1.x
+// keeps no creation or update time for a record, so there's no timestamp
+// to project, store one in a field if it's needed.
+pub async fn select_with_meta(
+    db: &Surreal<Db>,
+    table: &str,
+) -> Result<Vec<RecordMeta>, Box<dyn Error>> {
+    let mut response = bound_table(
+        db,
+        "SELECT meta::id(id) AS rid, meta::tb(id) AS tbl FROM type::table($tbl)",
+        table,
+    )
+    .await?;
+    Ok(response.take(0)?)
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    // Create a new SurrealDB instance
-    let db = Surreal::new::<Mem>(()).await?;
-    dbg!(&db);
+// Number of records in `table`. An empty table returns no rows at all
+// rather than `{ count: 0 }`, so that's mapped to 0.
+pub async fn query_count(db: &Surreal<Db>, table: &str) -> Result<usize, Box<dyn Error>> {
+    let mut response =
+        bound_table(db, "SELECT count() FROM type::table($tbl) GROUP ALL", table).await?;
+    let result: Option<CountResult> = response.take(0)?;
+    Ok(result.map_or(0, |r| r.count))
+}
 
-    db.use_ns("test").use_db("test").await?;
+// Names of the tables in the current database, sorted. Writing to a table
+// defines it, so this includes tables that were never `DEFINE`d.
+pub async fn list_tables(db: &Surreal<Db>) -> Result<Vec<String>, RecordIdError> {
+    let mut response = db.query("INFO FOR DB").await?;
+    let info: Option<serde_json::Value> = response.take(0)?;
+    let mut tables: Vec<String> = info
+        .as_ref()
+        .and_then(|i| i.get("tables"))
+        .and_then(|t| t.as_object())
+        .map(|t| t.keys().cloned().collect())
+        .unwrap_or_default();
+    tables.sort();
+    Ok(tables)
+}
+
+// `db.select` that returns `RecordIdError::UnknownTable` for a table that
+// doesn't exist, where `db.select` gives an empty Vec, so a misspelled
+// table name isn't mistaken for an empty one.
+pub async fn select_all_strict<T: DeserializeOwned>(
+    db: &Surreal<Db>,
+    table: &str,
+) -> Result<Vec<T>, RecordIdError> {
+    if !list_tables(db).await?.iter().any(|t| t == table) {
+        return Err(RecordIdError::UnknownTable {
+            table: table.to_owned(),
+        });
+    }
+    Ok(db.select(table).await?)
+}
+
+// `db.select` for tables that might be too big to hold in memory: when
+// `table` has more than `limit` rows nothing is loaded and
+// `RecordIdError::TooManyRows` is returned, page through it with
+// `select_keyset` instead. The select is also capped at `limit` in case
+// rows are added after the count.
+pub async fn select_all_bounded<T: DeserializeOwned>(
+    db: &Surreal<Db>,
+    table: &str,
+    limit: usize,
+) -> Result<Vec<T>, RecordIdError> {
+    let mut response = bound_table(
+        db,
+        "SELECT count() FROM type::table($tbl) GROUP ALL; \
+         SELECT * FROM type::table($tbl) LIMIT $limit;",
+        table,
+    )
+    .bind(("limit", limit))
+    .await?;
+    let count = response
+        .take::<Option<CountResult>>(0)?
+        .map_or(0, |r| r.count);
+    if count > limit {
+        return Err(RecordIdError::TooManyRows { count, limit });
+    }
+    Ok(response.take(1)?)
+}
+
+// Number of distinct values of `field` in `table`. SurrealQL has no
+// `count(DISTINCT ..)`, so the values are deduplicated with
+// `array::distinct`. Rows without `field` count as one `NONE` value.
+pub async fn count_distinct(
+    db: &Surreal<Db>,
+    table: &str,
+    field: &str,
+) -> Result<usize, Box<dyn Error>> {
+    validate_field(field)?;
+    let sql = format!(
+        "RETURN array::len(array::distinct((SELECT VALUE {field} FROM type::table($tbl))))"
+    );
+    let mut response = bound_table(db, &sql, table).await?;
+    let count: Option<usize> = response.take(0)?;
+    Ok(count.unwrap_or(0))
+}
+
+// `building_tbl` bucketed by address. SurrealDB doesn't keep insertion
+// order, so each bucket is in id order, the creation order for ids that
+// increase.
+pub async fn group_by_address(
+    db: &Surreal<Db>,
+) -> Result<BTreeMap<String, Vec<Building>>, Box<dyn Error>> {
+    let mut response = db
+        .query("SELECT id, address FROM building_tbl ORDER BY id")
+        .await?;
+    let buildings: Vec<Building> = response.take(0)?;
+    let mut groups: BTreeMap<String, Vec<Building>> = BTreeMap::new();
+    for building in buildings {
+        groups
+            .entry(building.address.clone())
+            .or_default()
+            .push(building);
+    }
+    Ok(groups)
+}
+
+// The `building_tbl` records whose address is one of `addresses`. An empty
+// slice matches nothing, so it returns without a query.
+pub async fn select_addresses_in(
+    db: &Surreal<Db>,
+    addresses: &[&str],
+) -> Result<Vec<BuildingWithThing>, Box<dyn Error>> {
+    if addresses.is_empty() {
+        return Ok(Vec::new());
+    }
+    let list: Vec<String> = addresses.iter().map(|a| a.to_string()).collect();
+    let mut response = db
+        .query("SELECT * FROM building_tbl WHERE address IN $list")
+        .bind(("list", list))
+        .await?;
+    Ok(response.take(0)?)
+}
+
+// Histogram of `building_tbl` addresses, address -> number of records
+pub async fn count_by_address(db: &Surreal<Db>) -> Result<HashMap<String, usize>, Box<dyn Error>> {
+    let mut response = db
+        .query("SELECT address, count() AS n FROM building_tbl GROUP BY address")
+        .await?;
+    let rows: Vec<AddressCount> = response.take(0)?;
+    Ok(rows.into_iter().map(|r| (r.address, r.n)).collect())
+}
+
+// Make `table` schemafull with the building shape the demo uses, a
+// string `address` and the optional `deleted_at` that `soft_delete` sets.
+// A schemafull table silently drops undefined fields, so without it a
+// soft delete would report success and store nothing. `DEFINE` won't take
+// a parameter for the table so the name is interpolated and has to pass
+// `validate_field`.
+pub async fn ensure_building_schema(db: &Surreal<Db>, table: &str) -> Result<(), Box<dyn Error>> {
+    validate_field(table)?;
+    db.query(format!(
+        "DEFINE TABLE {table} SCHEMAFULL; \
+         DEFINE FIELD address ON {table} TYPE string; \
+         DEFINE FIELD deleted_at ON {table} TYPE option<datetime>;"
+    ))
+    .await?
+    .check()?;
+    Ok(())
+}
+
+// `db.select` into `BuildingWithRidOptionString` that gives the same
+// rows whether or not `ensure_building_schema` ran. A schemaless table
+// keeps whatever fields were written, a stray stored `rid` included, and
+// a schemafull one drops them, so only `address` is projected and `rid`
+// is always None.
+pub async fn select_all_consistent(
+    db: &Surreal<Db>,
+    table: &str,
+) -> Result<Vec<BuildingWithRidOptionString>, Box<dyn Error>> {
+    let mut response = bound_table(db, "SELECT address FROM type::table($tbl)", table).await?;
+    Ok(response.take(0)?)
+}
+
+// `db.select` into `BuildingWithRidOptionString` always leaves `rid` as
+// None (see `test_select`). Select with the `Thing` id instead and backfill
+// `rid` from it so the result matches the `meta::id` query path.
+pub async fn select_fill_rid(
+    db: &Surreal<Db>,
+    table: &str,
+) -> Result<Vec<BuildingWithRidOptionString>, Box<dyn Error>> {
+    let results: Vec<BuildingWithThing> = db.select(table).await?;
+    Ok(results
+        .into_iter()
+        .map(|b| BuildingWithRidOptionString {
+            rid: Some(b.id.get_id()),
+            address: b.address,
+        })
+        .collect())
+}
+
+// The first row of `table` with the `rid` projection, None when it's
+// empty. Cheaper than `select_all_flat` for "does any building exist?".
+pub async fn select_first<T: DeserializeOwned>(
+    db: &Surreal<Db>,
+    table: &str,
+) -> Result<Option<T>, Box<dyn Error>> {
+    let mut response = bound_table(
+        db,
+        "SELECT *, meta::id(id) AS rid FROM type::table($tbl) LIMIT 1",
+        table,
+    )
+    .await?;
+    Ok(response.take(0)?)
+}
+
+// Rows of `table` matching `where_clause`, with the `rid` projection.
+// `where_clause` is pasted into the query so it must only refer to values
+// through `$param` placeholders bound in `binds`, never interpolate them.
+// `$tbl` is taken by the table binding.
+pub async fn select_where<T: DeserializeOwned>(
+    db: &Surreal<Db>,
+    table: &str,
+    where_clause: &str,
+    binds: Vec<(&str, surrealdb::sql::Value)>,
+) -> Result<Vec<T>, Box<dyn Error>> {
+    let sql = format!("SELECT *, meta::id(id) AS rid FROM type::table($tbl) WHERE {where_clause}");
+    let mut query = bound_table(db, &sql, table);
+    for (name, value) in binds {
+        query = query.bind((name.to_owned(), value));
+    }
+    let mut response = query.await?;
+    Ok(response.take(0)?)
+}
+
+// Rows of `table` as they were at `at`, which needs `SELECT ... VERSION`.
+// None of the engines SurrealDB 1.5 can run here keep versions, they parse
+// the clause and then ignore it and return the current rows, so rather
+// than pass those off as the past this is always
+// `RecordIdError::VersioningUnsupported`.
+pub async fn select_as_of<T: DeserializeOwned>(
+    db: &Surreal<Db>,
+    table: &str,
+    at: Datetime,
+) -> Result<Vec<T>, RecordIdError> {
+    let _ = (db, table, at);
+    Err(RecordIdError::VersioningUnsupported)
+}
+
+// `db.select` wrapped in `tokio::time::timeout` so a hung remote engine
+// can't block the caller forever.
+pub async fn select_all_timeout<T: DeserializeOwned>(
+    db: &Surreal<Db>,
+    table: &str,
+    dur: Duration,
+) -> Result<Vec<T>, RecordIdError> {
+    match tokio::time::timeout(dur, db.select::<Vec<T>>(table).into_future()).await {
+        Ok(results) => Ok(results?),
+        Err(_) => Err(RecordIdError::Timeout(dur)),
+    }
+}
+
+// Errors worth retrying: the remote transport failing, e.g. a `Ws`
+// connection reset, rather than the query itself being wrong.
+pub fn is_transient(e: &surrealdb::Error) -> bool {
+    use surrealdb::error::{Api, Db as DbError};
+    matches!(
+        e,
+        surrealdb::Error::Api(Api::Ws(_) | Api::Http(_))
+            | surrealdb::Error::Db(DbError::QueryTimedout)
+    )
+}
+
+// Pause between attempts of `retry_transient`
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+// Run `op` up to `attempts` times (at least once) while it fails with an
+// `is_transient` error. Any other error, or the last transient one, is
+// returned.
+pub async fn retry_transient<T, F, Fut>(attempts: u32, mut op: F) -> Result<T, RecordIdError>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = surrealdb::Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Ok(v) => return Ok(v),
+            Err(e) if attempt < attempts && is_transient(&e) => {
+                attempt += 1;
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+// `Surreal::init()` handles that were never connected, or lost their
+// connection, fail every request with this
+pub fn is_uninitialised(e: &surrealdb::Error) -> bool {
+    matches!(
+        e,
+        surrealdb::Error::Api(surrealdb::error::Api::ConnectionUninitialised)
+    )
+}
+
+// Run `op`, and if it fails with `is_uninitialised` run `reconnect` and
+// retry `op` once. `reconnect` is whatever connected the handle in the
+// first place, e.g. `db.connect::<Ws>(addr)` followed by `signin` and
+// `use_ns`.
+pub async fn with_auto_reconnect<T, Op, OpFut, Re, ReFut>(
+    mut op: Op,
+    reconnect: Re,
+) -> Result<T, RecordIdError>
+where
+    Op: FnMut() -> OpFut,
+    OpFut: std::future::Future<Output = surrealdb::Result<T>>,
+    Re: FnOnce() -> ReFut,
+    ReFut: std::future::Future<Output = surrealdb::Result<()>>,
+{
+    match op().await {
+        Err(e) if is_uninitialised(&e) => {
+            reconnect().await?;
+            Ok(op().await?)
+        }
+        result => Ok(result?),
+    }
+}
+
+// Every record of `table` in id order, fetched `page_size` at a time and
+// resuming after the last id seen when the connection drops. A page that
+// fails with an `is_transient` or `is_uninitialised` error is retried up
+// to `attempts` times in all, running `reconnect` first (see
+// `with_auto_reconnect`), so no row is yielded twice or skipped. Other
+// errors, and the last failed attempt, end the stream.
+pub fn stream_all_resumable<'a, C, Re, ReFut>(
+    db: &'a Surreal<C>,
+    table: &'a str,
+    page_size: usize,
+    attempts: u32,
+    reconnect: Re,
+) -> impl Stream<Item = Result<BuildingWithThing, RecordIdError>> + 'a
+where
+    C: Connection,
+    Re: FnMut() -> ReFut + 'a,
+    ReFut: std::future::Future<Output = surrealdb::Result<()>> + 'a,
+{
+    struct State<Re> {
+        after: Option<Thing>,
+        done: bool,
+        reconnect: Re,
+    }
+
+    let state = State {
+        after: None,
+        done: false,
+        reconnect,
+    };
+    futures::stream::unfold(state, move |mut state| async move {
+        if state.done {
+            return None;
+        }
+        let mut attempt = 1;
+        let page = loop {
+            let filter = if state.after.is_some() {
+                "WHERE id > $after"
+            } else {
+                ""
+            };
+            let sql = format!("SELECT * FROM type::table($tbl) {filter} ORDER BY id LIMIT $limit");
+            let result = async {
+                let mut response = db
+                    .query(sql)
+                    .bind(("tbl", table))
+                    .bind(("after", &state.after))
+                    .bind(("limit", page_size))
+                    .await?;
+                response.take::<Vec<BuildingWithThing>>(0)
+            }
+            .await;
+            match result {
+                Ok(page) => break page,
+                Err(e) if attempt < attempts && (is_transient(&e) || is_uninitialised(&e)) => {
+                    attempt += 1;
+                    tokio::time::sleep(RETRY_DELAY).await;
+                    // A failed reconnect is just another failed attempt
+                    let _ = (state.reconnect)().await;
+                }
+                Err(e) => {
+                    state.done = true;
+                    return Some((vec![Err(e.into())], state));
+                }
+            }
+        };
+        if page.len() < page_size {
+            state.done = true;
+        }
+        state.after = page.last().map(|b| b.id.clone());
+        if page.is_empty() {
+            return None;
+        }
+        Some((page.into_iter().map(Ok).collect::<Vec<_>>(), state))
+    })
+    .flat_map(futures::stream::iter)
+}
+
+// `db.select` retried on transient errors, this is for remote engines
+// where a connection reset shouldn't fail the whole select.
+pub async fn select_all_retry<C: Connection, T: DeserializeOwned>(
+    db: &Surreal<C>,
+    table: &str,
+    attempts: u32,
+) -> Result<Vec<T>, RecordIdError> {
+    retry_transient(attempts, || db.select::<Vec<T>>(table).into_future()).await
+}
+
+// Create `table:id` unless it already exists. Returns `true` if a new
+// record was created and `false` if the id was already taken, any other
+// failure is still an error.
+pub async fn insert_or_ignore(
+    db: &Surreal<Db>,
+    table: &str,
+    id: &str,
+    address: &str,
+) -> Result<bool, Box<dyn Error>> {
+    let response = db
+        .query(r#"CREATE type::thing($tbl, $id) SET address = $addr;"#)
+        .bind(("tbl", table))
+        .bind(("id", id))
+        .bind(("addr", address))
+        .await?;
+    match response.check() {
+        Ok(_) => Ok(true),
+        Err(surrealdb::Error::Db(surrealdb::error::Db::RecordExists { .. })) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Create one record per `(id, address)` pair in a single transaction,
+// returning the created Things in the same order as `rows`. If any id
+// already exists nothing is created. `INSERT INTO` can't be used: it only
+// takes a table name or a parameter, and SurrealDB 1.5 builds the ids from
+// the parameter's escaped name, so `spaced tbl` would be stored as the
+// table `` `spaced tbl` ``. `type::thing` takes the name literally.
+pub async fn create_buildings(
+    db: &Surreal<Db>,
+    table: &str,
+    rows: &[(String, String)],
+) -> Result<Vec<Thing>, Box<dyn Error>> {
+    write_buildings(db, table, rows, "CREATE").await
+}
+
+// The `FOR` loop shared by `create_buildings` and `bulk_upsert`, `verb`
+// being `CREATE` or `UPDATE`
+async fn write_buildings(
+    db: &Surreal<Db>,
+    table: &str,
+    rows: &[(String, String)],
+    verb: &str,
+) -> Result<Vec<Thing>, Box<dyn Error>> {
+    let records: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|(id, address)| serde_json::json!({ "id": id, "address": address }))
+        .collect();
+    let sql = format!(
+        "BEGIN; \
+         FOR $r IN $records {{ {verb} type::thing($tbl, $r.id) SET address = $r.address; }}; \
+         COMMIT;"
+    );
+    bound_table(db, &sql, table)
+        .bind(("records", records))
+        .await?
+        .check()?;
+    Ok(rows
+        .iter()
+        .map(|(id, _)| Thing::from((table, id.as_str())))
+        .collect())
+}
+
+// Create `table:id`. `id` is stored as a string id even when it's all
+// digits, `"1234567890"` and `1234567890` are different records with
+// different escaping. With `coerce_numeric_ids` an all-digit `id` that
+// fits an `i64` is stored as `Id::Number` instead.
+pub async fn create_building(
+    db: &Surreal<Db>,
+    table: &str,
+    id: &str,
+    address: &str,
+    coerce_numeric_ids: bool,
+) -> Result<Thing, Box<dyn Error>> {
+    let id = match id.parse::<i64>() {
+        Ok(n) if coerce_numeric_ids && id.bytes().all(|b| b.is_ascii_digit()) => Id::Number(n),
+        _ => Id::String(id.to_owned()),
+    };
+    let created: Option<BuildingWithThing> = db
+        .create((table, id))
+        .content(Building {
+            address: address.to_owned(),
+        })
+        .await?;
+    created
+        .map(|b| b.id)
+        .ok_or_else(|| format!("create in {table} returned nothing").into())
+}
+
+// Increment the `seq_counter:<counter_name>` record and return its new
+// value, 1 the first time. `UPDATE` creates the record if it's missing
+// and the increment happens inside the statement, so concurrent callers
+// each get their own value.
+pub async fn next_seq(db: &Surreal<Db>, counter_name: &str) -> Result<i64, Box<dyn Error>> {
+    let mut response = db
+        .query("UPDATE type::thing('seq_counter', $name) SET value += 1 RETURN VALUE value")
+        .bind(("name", counter_name))
+        .await?;
+    let value: Option<i64> = response.take(0)?;
+    value.ok_or_else(|| format!("counter {counter_name} returned no value").into())
+}
+
+// Create a record in `table` whose numeric id is the next value of the
+// `table` counter
+pub async fn create_with_seq(
+    db: &Surreal<Db>,
+    table: &str,
+    address: &str,
+) -> Result<Thing, Box<dyn Error>> {
+    let id = next_seq(db, table).await?;
+    let created: Option<BuildingWithThing> = db
+        .create((table, id))
+        .content(Building {
+            address: address.to_owned(),
+        })
+        .await?;
+    created
+        .map(|b| b.id)
+        .ok_or_else(|| format!("create of {table}:{id} returned nothing").into())
+}
+
+// Whether `upsert_building` made a new record or changed an existing one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpsertAction {
+    Created,
+    Updated,
+}
+
+// Set the address of `table:id`, creating the record if it's missing, and
+// say which happened. `UPDATE` creates missing records, and `$before` is
+// NONE exactly when it did, so one atomic statement answers both.
+pub async fn upsert_building(
+    db: &Surreal<Db>,
+    table: &str,
+    id: &str,
+    address: &str,
+) -> Result<(BuildingWithThing, UpsertAction), Box<dyn Error>> {
+    #[derive(Deserialize)]
+    struct Upserted {
+        before: Option<BuildingWithThing>,
+        after: BuildingWithThing,
+    }
+
+    let mut response = db
+        .query(
+            "UPDATE type::thing($tbl, $id) SET address = $address \
+             RETURN $before AS before, $after AS after",
+        )
+        .bind(("tbl", table))
+        .bind(("id", id))
+        .bind(("address", address))
+        .await?;
+    let upserted: Option<Upserted> = response.take(0)?;
+    let upserted = upserted.ok_or_else(|| format!("upsert of {table}:{id} returned nothing"))?;
+    let action = match upserted.before {
+        None => UpsertAction::Created,
+        Some(_) => UpsertAction::Updated,
+    };
+    Ok((upserted.after, action))
+}
+
+// `create_buildings` that updates the address of ids that already exist
+// instead of failing, so seeding the same rows twice is idempotent. An
+// `UPDATE` of a missing record creates it.
+pub async fn bulk_upsert(
+    db: &Surreal<Db>,
+    table: &str,
+    rows: &[(String, String)],
+) -> Result<Vec<Thing>, Box<dyn Error>> {
+    write_buildings(db, table, rows, "UPDATE").await
+}
+
+// Select the record for `thing`, bound as `$rec` rather than as a
+// `tbl:id` string, so the id keeps its type: `building_tbl:1` and
+// `building_tbl:⟨1⟩` are different records and this finds the right one.
+pub async fn select_by_thing_bound(
+    db: &Surreal<Db>,
+    thing: &Thing,
+) -> Result<Option<BuildingWithThing>, Box<dyn Error>> {
+    let mut response = db.query("SELECT * FROM $rec").bind(("rec", thing)).await?;
+    Ok(response.take(0)?)
+}
+
+// Select the records for `things` in one query, missing ids are skipped.
+pub async fn select_by_things(
+    db: &Surreal<Db>,
+    things: &[Thing],
+) -> Result<Vec<BuildingWithThing>, Box<dyn Error>> {
+    let mut response = db
+        .query("SELECT * FROM $things")
+        .bind(("things", things.to_vec()))
+        .await?;
+    let results: Vec<BuildingWithThing> = response.take(0)?;
+    Ok(results)
+}
+
+// Estimated heap bytes owned by `t`, the table string plus the id's
+// payload, for budgeting caches of many ids. Excludes `size_of::<Thing>()`
+// itself, and map nodes are counted as one `(String, Value)` per entry.
+pub fn thing_heap_size(t: &Thing) -> usize {
+    use std::mem::size_of;
+    use surrealdb::sql::{Array, Object, Value};
+
+    fn array_heap_size(a: &Array) -> usize {
+        a.0.capacity() * size_of::<Value>() + a.0.iter().map(value_heap_size).sum::<usize>()
+    }
+
+    fn object_heap_size(o: &Object) -> usize {
+        o.0.iter()
+            .map(|(k, v)| size_of::<(String, Value)>() + k.capacity() + value_heap_size(v))
+            .sum()
+    }
+
+    fn value_heap_size(v: &Value) -> usize {
+        match v {
+            Value::Strand(s) => s.0.capacity(),
+            Value::Array(a) => array_heap_size(a),
+            Value::Object(o) => object_heap_size(o),
+            Value::Thing(t) => size_of::<Thing>() + thing_heap_size(t),
+            // Numbers, bools and the like are inline, other values don't
+            // appear in ids
+            _ => 0,
+        }
+    }
+
+    let id = match &t.id {
+        Id::String(s) => s.capacity(),
+        Id::Array(a) => array_heap_size(a),
+        Id::Object(o) => object_heap_size(o),
+        // Numbers and `Id::Generate` are inline
+        _ => 0,
+    };
+    t.tb.capacity() + id
+}
+
+// Order Things by table then id, comparing ids numerically when both are
+// numbers (or strings of digits like "1234567890"). When only one id is
+// numeric it sorts first, otherwise the unescaped ids compare as strings.
+pub fn cmp_things(a: &Thing, b: &Thing) -> Ordering {
+    fn numeric(t: &Thing) -> Option<i64> {
+        match &t.id {
+            Id::Number(n) => Some(*n),
+            _ => t.get_id().parse().ok(),
+        }
+    }
+
+    a.tb.cmp(&b.tb)
+        .then_with(|| match (numeric(a), numeric(b)) {
+            (Some(x), Some(y)) => x.cmp(&y),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => a.get_id().cmp(&b.get_id()),
+        })
+}
+
+// A `Thing` that sorts with `cmp_things`, so "2" comes before "10"
+#[derive(Debug, Clone)]
+pub struct SortableThing(pub Thing);
+
+impl Ord for SortableThing {
+    fn cmp(&self, other: &Self) -> Ordering {
+        cmp_things(&self.0, &other.0)
+    }
+}
+
+impl PartialOrd for SortableThing {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SortableThing {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortableThing {}
+
+// The namespace and database `main` uses, read from `SURREAL_NS` and
+// `SURREAL_DB` with the original hardcoded values as defaults. There's no
+// URL setting, the helpers all take a local `Surreal<Db>` so `main` always
+// uses the in-memory engine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub ns: String,
+    pub db: String,
+}
+
+pub fn config_from_env() -> Config {
+    config_from(|name| std::env::var(name).ok())
+}
+
+// `config_from_env` with the variables looked up through `var`, so a
+// test can pass a map instead of changing the process environment.
+pub fn config_from(var: impl Fn(&str) -> Option<String>) -> Config {
+    let var = |name: &str, default: &str| var(name).unwrap_or_else(|| default.to_owned());
+    Config {
+        ns: var("SURREAL_NS", "test"),
+        db: var("SURREAL_DB", "test"),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Owner {
+    pub id: Thing,
+    pub name: String,
+}
+
+// `owner` is a record link, `FETCH owner` replaces the link with the
+// linked record so it deserializes as an `Owner` rather than a `Thing`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BuildingWithOwner {
+    pub id: Thing,
+    pub address: String,
+    pub owner: Option<Owner>,
+}
+
+pub async fn select_with_owner(
+    db: &Surreal<Db>,
+    table: &str,
+) -> Result<Vec<BuildingWithOwner>, Box<dyn Error>> {
+    let mut response =
+        bound_table(db, "SELECT * FROM type::table($tbl) FETCH owner", table).await?;
+    let results: Vec<BuildingWithOwner> = response.take(0)?;
+    Ok(results)
+}
+
+// `select_with_owner` for any `T` and any record links, each `fetch`
+// field is replaced by the record it links to. A field may be a dotted
+// path such as `owner.company`, every part must pass `validate_field`.
+pub async fn select_all_fetch<T: DeserializeOwned>(
+    db: &Surreal<Db>,
+    table: &str,
+    fetch: &[&str],
+) -> Result<Vec<T>, Box<dyn Error>> {
+    for field in fetch {
+        if field.split('.').any(|part| validate_field(part).is_err()) {
+            return Err(RecordIdError::InvalidField(field.to_string()).into());
+        }
+    }
+    let mut sql = "SELECT * FROM type::table($tbl)".to_owned();
+    if !fetch.is_empty() {
+        sql = format!("{sql} FETCH {}", fetch.join(", "));
+    }
+    let mut response = bound_table(db, &sql, table).await?;
+    Ok(response.take(0)?)
+}
+
+// The lowest-level entry point, runs `sql` and hands back the untyped
+// `Response` so the caller can `.take(n)` each statement's result itself.
+pub async fn raw_query(db: &Surreal<Db>, sql: &str) -> Result<Response, Box<dyn Error>> {
+    Ok(db.query(sql).await?)
+}
+
+// Split a `get_tbl_id()` string into its table and unescaped id, so that
+// `split_tbl_id(&t.get_tbl_id())` is `t.get_tbl_id_tuple()` for any `t`.
+// Either part may be wrapped in `⟨` and `⟩` or quoted in backticks, see
+// `unescape_part`. An unescaped table ends at the first `:` and an
+// unescaped id runs to the end, so string ids containing colons stay intact.
+pub fn split_tbl_id(s: &str) -> Result<(String, String), RecordIdError> {
+    let parse_err = |what: &str| RecordIdError::Parse(format!("{what} in {s:?}"));
+    let (tbl, rest) = unescape_part(s, Some(':')).map_err(parse_err)?;
+    let rest = rest
+        .strip_prefix(':')
+        .ok_or_else(|| parse_err("missing `:`"))?;
+    let (id, rest) = unescape_part(rest, None).map_err(parse_err)?;
+    if !rest.is_empty() {
+        return Err(parse_err("trailing characters after the id"));
+    }
+    if tbl.is_empty() || id.is_empty() {
+        return Err(parse_err("empty table or id"));
+    }
+    Ok((tbl.into_owned(), id.into_owned()))
+}
+
+// The `tbl` or `id` part at the start of `s` with its escaping removed,
+// and the rest of `s`. A `⟨..⟩` part ends at the first `⟩`, a backtick
+// quoted one at the first unescaped backtick with its `\` escapes undone,
+// the inverse of `tbl_id::write_part`. A plain part runs up to `end`, or
+// to the end of `s` if `end` is None or not found.
+fn unescape_part(s: &str, end: Option<char>) -> Result<(Cow<'_, str>, &str), &'static str> {
+    if let Some(inner) = s.strip_prefix('⟨') {
+        let close = inner.find('⟩').ok_or("unterminated `⟨`")?;
+        return Ok((
+            Cow::Borrowed(&inner[..close]),
+            &inner[close + '⟩'.len_utf8()..],
+        ));
+    }
+    if let Some(inner) = s.strip_prefix('`') {
+        let mut part = String::with_capacity(inner.len());
+        let mut chars = inner.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '`' => return Ok((Cow::Owned(part), &inner[i + 1..])),
+                '\\' => part.push(chars.next().ok_or("unterminated `\\`")?.1),
+                c => part.push(c),
+            }
+        }
+        return Err("unterminated backtick");
+    }
+    let at = end.and_then(|c| s.find(c)).unwrap_or(s.len());
+    Ok((Cow::Borrowed(&s[..at]), &s[at..]))
+}
+
+// Live query on `building_tbl`, yielding a notification for every create,
+// update and delete.
+pub async fn live_buildings(
+    db: &Surreal<Db>,
+) -> Result<method::Stream<'_, Db, Vec<BuildingWithThing>>, Box<dyn Error>> {
+    Ok(db.select("building_tbl").live().await?)
+}
+
+// `live_buildings` filtered client-side to the actions in `kinds`, errors
+// are always passed through.
+pub async fn live_buildings_filtered<'a>(
+    db: &'a Surreal<Db>,
+    kinds: &[Action],
+) -> Result<
+    impl Stream<Item = surrealdb::Result<Notification<BuildingWithThing>>> + 'a,
+    Box<dyn Error>,
+> {
+    let kinds = kinds.to_vec();
+    let stream = live_buildings(db).await?;
+    Ok(stream.filter(move |n| {
+        future::ready(match n {
+            Ok(n) => kinds.contains(&n.action),
+            Err(_) => true,
+        })
+    }))
+}
+
+// Write `table` as CSV with a `rid,address` header, one line per record
+// using the unescaped id. Returns the number of records written.
+pub async fn export_csv<W: Write>(
+    db: &Surreal<Db>,
+    table: &str,
+    out: &mut W,
+) -> Result<usize, Box<dyn Error>> {
+    // Quote fields containing a delimiter, quote or newline, doubling quotes
+    fn field(s: &str) -> Cow<'_, str> {
+        if s.contains([',', '"', '\n', '\r']) {
+            Cow::Owned(format!("\"{}\"", s.replace('"', "\"\"")))
+        } else {
+            Cow::Borrowed(s)
+        }
+    }
+
+    let buildings = select_all_flat(db, table).await?;
+    writeln!(out, "rid,address")?;
+    for b in &buildings {
+        writeln!(out, "{},{}", field(&b.rid), field(&b.address))?;
+    }
+    Ok(buildings.len())
+}
+
+// A re-parseable `tbl:id` for a string id, the `get_tbl_id` of that
+// `Thing`. The id is wrapped in `⟨` and `⟩` only when it's numeric (so it
+// stays a string when parsed) or contains characters other than ASCII
+// alphanumerics and `_`. That matches `Thing::to_raw` except for an id
+// containing `⟩`, which is quoted in backticks instead since SurrealDB
+// can't parse its own `\⟩` back (see `tbl_id::write_tbl_id_with`).
+pub fn id_from_parts(table: &str, id: &str) -> String {
+    Thing::from((table, id)).get_tbl_id()
+}
+
+// Everything but the RFC 3986 unreserved characters is percent-encoded
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+// The unescaped id as a URL path segment, e.g. `1234567890` for
+// `/buildings/1234567890`, with `/`, spaces and the like percent-encoded.
+// A string id that would read back as a number, such as the demo's
+// `⟨1234567890⟩`, keeps its `⟨` and `⟩` (`%E2%9F%A8..%E2%9F%A9`) so it
+// isn't mistaken for `Id::Number`, as does one already wrapped in them.
+pub fn thing_to_path_segment(t: &Thing) -> String {
+    match &t.id {
+        Id::String(s) if s.parse::<i64>().is_ok() || is_angle_wrapped(s) => {
+            utf8_percent_encode(&format!("⟨{s}⟩"), PATH_SEGMENT).to_string()
+        }
+        _ => utf8_percent_encode(&t.get_id_cow(), PATH_SEGMENT).to_string(),
+    }
+}
+
+fn is_angle_wrapped(s: &str) -> bool {
+    s.starts_with('⟨') && s.ends_with('⟩') && s.len() > '⟨'.len_utf8()
+}
+
+// The reverse of `thing_to_path_segment`. A segment wrapped in `⟨` and
+// `⟩` is a string id with those stripped, otherwise one that parses as an
+// `i64` becomes `Id::Number` and anything else `Id::String`.
+pub fn thing_from_path_segment(table: &str, seg: &str) -> Result<Thing, RecordIdError> {
+    let id = percent_decode_str(seg)
+        .decode_utf8()
+        .map_err(|e| RecordIdError::Parse(format!("path segment {seg:?}: {e}")))?;
+    if id.is_empty() {
+        return Err(RecordIdError::Parse("empty path segment".to_owned()));
+    }
+    let id = if is_angle_wrapped(&id) {
+        Id::String(id['⟨'.len_utf8()..id.len() - '⟩'.len_utf8()].to_owned())
+    } else {
+        match id.parse::<i64>() {
+            Ok(n) => Id::Number(n),
+            Err(_) => Id::String(id.into_owned()),
+        }
+    };
+    Ok(Thing::from((table, id)))
+}
+
+// Wraps a `Surreal<Db>` and caches `select_all` results per table for
+// `ttl`. The write methods below invalidate the table they write to, any
+// other write, such as one made directly through `db()`, isn't seen until
+// the entry expires unless followed by an explicit `invalidate`.
+pub struct CachedStore {
+    db: Surreal<Db>,
+    ttl: Duration,
+    cache: Mutex<HashMap<String, (Instant, Vec<BuildingWithRidString>)>>,
+    db_selects: AtomicUsize,
+}
+
+impl CachedStore {
+    pub fn new(db: Surreal<Db>, ttl: Duration) -> Self {
+        CachedStore {
+            db,
+            ttl,
+            cache: Mutex::new(HashMap::new()),
+            db_selects: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn db(&self) -> &Surreal<Db> {
+        &self.db
+    }
+
+    // How many times `select_all` went to the database
+    pub fn db_selects(&self) -> usize {
+        self.db_selects.load(AtomicOrdering::Relaxed)
+    }
+
+    pub fn invalidate(&self, table: &str) {
+        self.cache.lock().unwrap().remove(table);
+    }
+
+    pub async fn select_all(
+        &self,
+        table: &str,
+    ) -> Result<Vec<BuildingWithRidString>, Box<dyn Error>> {
+        if let Some((at, rows)) = self.cache.lock().unwrap().get(table) {
+            if at.elapsed() < self.ttl {
+                return Ok(rows.clone());
+            }
+        }
+
+        self.db_selects.fetch_add(1, AtomicOrdering::Relaxed);
+        let rows = select_all_flat(&self.db, table).await?;
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(table.to_owned(), (Instant::now(), rows.clone()));
+        Ok(rows)
+    }
+
+    pub async fn create_buildings(
+        &self,
+        table: &str,
+        rows: &[(String, String)],
+    ) -> Result<Vec<Thing>, Box<dyn Error>> {
+        let result = create_buildings(&self.db, table, rows).await;
+        self.invalidate(table);
+        result
+    }
+
+    pub async fn insert_or_ignore(
+        &self,
+        table: &str,
+        id: &str,
+        address: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        let result = insert_or_ignore(&self.db, table, id, address).await;
+        self.invalidate(table);
+        result
+    }
+
+    pub async fn create_building(
+        &self,
+        table: &str,
+        id: &str,
+        address: &str,
+        coerce_numeric_ids: bool,
+    ) -> Result<Thing, Box<dyn Error>> {
+        let result = create_building(&self.db, table, id, address, coerce_numeric_ids).await;
+        self.invalidate(table);
+        result
+    }
+
+    pub async fn upsert_building(
+        &self,
+        table: &str,
+        id: &str,
+        address: &str,
+    ) -> Result<(BuildingWithThing, UpsertAction), Box<dyn Error>> {
+        let result = upsert_building(&self.db, table, id, address).await;
+        self.invalidate(table);
+        result
+    }
+
+    pub async fn bulk_upsert(
+        &self,
+        table: &str,
+        rows: &[(String, String)],
+    ) -> Result<Vec<Thing>, Box<dyn Error>> {
+        let result = bulk_upsert(&self.db, table, rows).await;
+        self.invalidate(table);
+        result
+    }
+
+    pub async fn change_id(&self, from: &Thing, new_id: &str) -> Result<Thing, Box<dyn Error>> {
+        let result = change_id(&self.db, from, new_id).await;
+        self.invalidate(&from.tb);
+        result
+    }
+
+    pub async fn soft_delete(&self, thing: &Thing) -> Result<bool, Box<dyn Error>> {
+        let result = soft_delete(&self.db, thing).await;
+        self.invalidate(&thing.tb);
+        result
+    }
+
+    pub async fn delete_by_thing(
+        &self,
+        table: &str,
+        thing: &Thing,
+    ) -> Result<Option<BuildingWithThing>, RecordIdError> {
+        let result = delete_by_thing(&self.db, table, thing).await;
+        self.invalidate(table);
+        result
+    }
+}
+
+// SQL run many times with different binds, e.g. in a request handler.
+// SurrealDB parses the text on every `run`, this just keeps it in one
+// place. Only the first statement's rows are returned.
+pub struct PreparedQuery {
+    sql: String,
+}
+
+impl PreparedQuery {
+    pub fn new(sql: impl Into<String>) -> Self {
+        PreparedQuery { sql: sql.into() }
+    }
+
+    pub async fn run<T: DeserializeOwned>(
+        &self,
+        db: &Surreal<Db>,
+        binds: impl Serialize,
+    ) -> Result<Vec<T>, Box<dyn Error>> {
+        let mut response = db.query(self.sql.as_str()).bind(binds).await?;
+        Ok(response.take(0)?)
+    }
+}
+
+// A record id whose `Display` is the unescaped `tbl:id`, the form to show
+// people. Use `to_escaped_string` when the string has to parse back into
+// the same record, e.g. in logs that get pasted into queries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordId(pub Thing);
+
+impl RecordId {
+    // The `⟨`/`⟩` escaped form from `get_tbl_id`
+    pub fn to_escaped_string(&self) -> String {
+        self.0.get_tbl_id()
+    }
+}
+
+impl From<Thing> for RecordId {
+    fn from(thing: Thing) -> Self {
+        RecordId(thing)
+    }
+}
+
+impl fmt::Display for RecordId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.0.get_tbl(), self.0.get_id())
+    }
+}
+
+// Serde for a `Thing` as a flat `"tbl:id"` string, for `#[serde(with)]`.
+// The string is the escaped `get_tbl_id` form rather than `RecordId`'s
+// unescaped Display: `building_tbl:1234567890` would come back as a
+// number even when the id was the string "1234567890". Deserializing also
+// takes the nested `{ tb, id }` form, which is what SurrealDB results use.
+pub mod thing_as_string {
+    use super::{parse_tbl_id, IdTraits};
+    use serde::{Deserialize, Deserializer, Serializer};
+    use surrealdb::sql::Thing;
+
+    pub fn serialize<S: Serializer>(thing: &Thing, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&thing.get_tbl_id())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Thing, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            String(String),
+            Thing(Thing),
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::String(s) => parse_tbl_id(&s).map_err(serde::de::Error::custom),
+            Repr::Thing(thing) => Ok(thing),
+        }
+    }
+}
+
+impl Serialize for RecordId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        thing_as_string::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RecordId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        thing_as_string::deserialize(deserializer).map(RecordId)
+    }
+}
+
+// `db.select` that retries with the `meta::id(id) AS rid` projection when
+// the rows don't deserialize into `T`, so `rid: String` structs work
+// without the caller picking the query. Other errors are returned as is.
+pub async fn try_select<T: DeserializeOwned>(
+    db: &Surreal<Db>,
+    table: &str,
+) -> Result<Vec<T>, RecordIdError> {
+    match db.select(table).await {
+        Ok(rows) => Ok(rows),
+        Err(surrealdb::Error::Api(surrealdb::error::Api::FromValue { .. })) => {
+            let mut response = bound_table(
+                db,
+                "SELECT *, meta::id(id) AS rid FROM type::table($tbl)",
+                table,
+            )
+            .await?;
+            Ok(response.take(0)?)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+// `db.select` that, when the rows don't deserialize into `T`, finds the
+// first row that doesn't and returns it as `RecordIdError::DeserializeRow`.
+// SurrealDB's own error names neither the row nor, readably, the field.
+pub async fn select_with_row_context<T: DeserializeOwned>(
+    db: &Surreal<Db>,
+    table: &str,
+) -> Result<Vec<T>, RecordIdError> {
+    let err = match db.select(table).await {
+        Ok(rows) => return Ok(rows),
+        Err(e @ surrealdb::Error::Api(surrealdb::error::Api::FromValue { .. })) => e,
+        Err(e) => return Err(e.into()),
+    };
+
+    // Only on the error path, so a query per row is fine
+    for index in 0.. {
+        let mut response = bound_table(
+            db,
+            "SELECT * FROM type::table($tbl) LIMIT 1 START $start",
+            table,
+        )
+        .bind(("start", index))
+        .await?;
+        match response.take::<Option<T>>(0) {
+            Ok(Some(_)) => {}
+            // Every row deserialized on its own, nothing to add
+            Ok(None) => break,
+            Err(surrealdb::Error::Api(surrealdb::error::Api::FromValue { value, error })) => {
+                let field = error
+                    .split("missing field `")
+                    .nth(1)
+                    .and_then(|rest| rest.split('`').next())
+                    .map(str::to_owned);
+                return Err(RecordIdError::DeserializeRow {
+                    index,
+                    field,
+                    raw: value.into_json(),
+                });
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(err.into())
+}
+
+// Guard against operating on a record from an unexpected table, e.g. when
+// the `Thing` came from untrusted input.
+pub fn expect_table(thing: &Thing, table: &str) -> Result<(), RecordIdError> {
+    if thing.tb == table {
+        Ok(())
+    } else {
+        Err(RecordIdError::WrongTable {
+            expected: table.to_owned(),
+            found: thing.tb.clone(),
+        })
+    }
+}
+
+// Select the record for a `get_tbl_id()` string, which must be in `table`
+pub async fn select_by_id(
+    db: &Surreal<Db>,
+    table: &str,
+    tbl_id: &str,
+) -> Result<Option<BuildingWithThing>, RecordIdError> {
+    let thing = parse_tbl_id(tbl_id)?;
+    expect_table(&thing, table)?;
+    Ok(db.select((thing.tb, thing.id)).await?)
+}
+
+// Select the record for a `get_tbl_id()` string, returning its `Thing`
+// along with the row as `T`, or None for the row when it doesn't
+// deserialize (say `T` has a field the record lacks). The `Thing` is the
+// stored record's own id, `RecordIdError::NotFound` if there's no record.
+pub async fn select_by_id_or_thing<T: DeserializeOwned>(
+    db: &Surreal<Db>,
+    tbl_id: &str,
+) -> Result<(Thing, Option<T>), RecordIdError> {
+    let thing = parse_tbl_id(tbl_id)?;
+    // `db.select` can't give a `Value`, a query result can
+    let mut response = db
+        .query("SELECT * FROM $thing")
+        .bind(("thing", &thing))
+        .await?;
+    let row = match response.take::<surrealdb::sql::Value>(0)? {
+        surrealdb::sql::Value::Array(rows) => rows.into_iter().next(),
+        _ => None,
+    };
+    let found = match &row {
+        Some(surrealdb::sql::Value::Object(obj)) => match obj.get("id") {
+            Some(surrealdb::sql::Value::Thing(found)) => Some(found.clone()),
+            _ => None,
+        },
+        _ => None,
+    };
+    match (found, row) {
+        (Some(found), Some(row)) => Ok((found, surrealdb::sql::from_value(row).ok())),
+        _ => Err(RecordIdError::NotFound(thing)),
+    }
+}
+
+// Delete `thing`, which must be in `table`, returning the deleted record
+pub async fn delete_by_thing(
+    db: &Surreal<Db>,
+    table: &str,
+    thing: &Thing,
+) -> Result<Option<BuildingWithThing>, RecordIdError> {
+    expect_table(thing, table)?;
+    Ok(db.delete((thing.tb.as_str(), thing.id.clone())).await?)
+}
+
+// Wait for the requests already sent on `db` to be handled, then drop it.
+// The engine runs requests in order, so a `ping` after them returns once
+// they're done. Nothing is flushed, the 1.5 SDK has no close or flush
+// call, and clones of `db` share the engine and keep it running.
+pub async fn drain_and_drop(db: Surreal<Db>) -> Result<(), Box<dyn Error>> {
+    ping(&db).await?;
+    drop(db);
+    tokio::task::yield_now().await;
+    Ok(())
+}
+
+// A fresh in-memory engine with `ns`/`db` selected. Every call is its own
+// datastore, so handles never share data even with the same names.
+pub async fn connect_mem_named(ns: &str, db: &str) -> Result<Surreal<Db>, Box<dyn Error>> {
+    let handle = Surreal::new::<Mem>(()).await?;
+    handle.use_ns(ns).use_db(db).await?;
+    Ok(handle)
+}
+
+// Round trip a trivial `RETURN 1` for readiness probes. Generic over the
+// connection so `Mem` and remote (`Ws`, `Http`) handles both work.
+pub async fn ping<C: Connection>(db: &Surreal<C>) -> Result<Duration, Box<dyn Error>> {
+    let start = Instant::now();
+    let mut response = db.query("RETURN 1").await?;
+    let one: Option<i64> = response.take(0)?;
+    if one != Some(1) {
+        return Err(format!("unexpected ping response {one:?}").into());
+    }
+    Ok(start.elapsed())
+}
+
+// Connect to the WebSocket server at `url`, e.g. `127.0.0.1:8000`, once
+// it's up, for test setups where the database container starts alongside
+// the tests. Connecting and `health()` are retried every `RETRY_DELAY`
+// until one passes, `RecordIdError::Timeout` after `timeout`.
+#[cfg(feature = "ws")]
+pub async fn wait_until_ready(
+    url: &str,
+    timeout: Duration,
+) -> Result<Surreal<surrealdb::engine::remote::ws::Client>, Box<dyn Error>> {
+    use surrealdb::engine::remote::ws::Ws;
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        let attempt = async {
+            let db = Surreal::new::<Ws>(url).await?;
+            db.health().await?;
+            Ok::<_, surrealdb::Error>(db)
+        };
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if let Ok(Ok(db)) = tokio::time::timeout(remaining, attempt).await {
+            return Ok(db);
+        }
+        if Instant::now() + RETRY_DELAY >= deadline {
+            return Err(RecordIdError::Timeout(timeout).into());
+        }
+        tokio::time::sleep(RETRY_DELAY).await;
+    }
+}
+
+// A record's `Thing` id alongside its payload. `id` serializes as a flat
+// `"tbl:id"` string, see `thing_as_string`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Record<T> {
+    #[serde(with = "thing_as_string")]
+    pub id: Thing,
+    pub data: T,
+}
+
+// Field names are interpolated into queries, only allow plain identifiers
+pub fn validate_field(field: &str) -> Result<(), RecordIdError> {
+    let mut chars = field.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(RecordIdError::InvalidField(field.to_owned()))
+    }
+}
+
+// Every row of `table` with its native `Thing` id, nothing stringified.
+// Keeps the id's type for passing back to `delete_by_thing` and the like.
+pub async fn select_all_things(
+    db: &Surreal<Db>,
+    table: &str,
+) -> Result<Vec<BuildingWithThing>, Box<dyn Error>> {
+    Ok(db.select(table).await?)
+}
+
+// All of `table` in id order, fetched `chunk_size` rows per query and
+// returned as those chunks, the last one may be shorter. Each chunk is a
+// `LIMIT`/`START` page, so rows written between pages can shift one over.
+pub async fn select_all_chunked<T: DeserializeOwned>(
+    db: &Surreal<Db>,
+    table: &str,
+    chunk_size: usize,
+) -> Result<Vec<Vec<T>>, Box<dyn Error>> {
+    if chunk_size == 0 {
+        return Err("chunk_size must be at least 1".into());
+    }
+    let mut chunks = Vec::new();
+    loop {
+        let mut response = bound_table(
+            db,
+            "SELECT *, meta::id(id) AS rid FROM type::table($tbl) \
+             ORDER BY id LIMIT $limit START $start",
+            table,
+        )
+        .bind(("limit", chunk_size))
+        .bind(("start", chunks.len() * chunk_size))
+        .await?;
+        let chunk: Vec<T> = response.take(0)?;
+        let last = chunk.len() < chunk_size;
+        if !chunk.is_empty() {
+            chunks.push(chunk);
+        }
+        if last {
+            return Ok(chunks);
+        }
+    }
+}
+
+// One page of `table` in id order starting after `after`, plus the cursor
+// to pass as `after` for the next page. The cursor is None once a page
+// comes back short, a full last page costs one more (empty) call.
+pub async fn select_keyset(
+    db: &Surreal<Db>,
+    table: &str,
+    after: Option<Thing>,
+    limit: usize,
+) -> Result<(Vec<BuildingWithThing>, Option<Thing>), Box<dyn Error>> {
+    let filter = if after.is_some() {
+        "WHERE id > $after"
+    } else {
+        ""
+    };
+    let sql = format!("SELECT * FROM type::table($tbl) {filter} ORDER BY id LIMIT $limit");
+    let mut response = bound_table(db, &sql, table)
+        .bind(("after", after))
+        .bind(("limit", limit))
+        .await?;
+    let page: Vec<BuildingWithThing> = response.take(0)?;
+    let cursor = match page.last() {
+        Some(last) if page.len() == limit => Some(last.id.clone()),
+        _ => None,
+    };
+    Ok((page, cursor))
+}
+
+// Just the ids of `table`, e.g. to copy them out without the payloads
+pub async fn select_ids(db: &Surreal<Db>, table: &str) -> Result<Vec<Thing>, Box<dyn Error>> {
+    let mut response = bound_table(db, "SELECT VALUE id FROM type::table($tbl)", table).await?;
+    Ok(response.take(0)?)
+}
+
+// Every row of `table` as an `(id, payload)` tuple, no wrapper struct
+pub async fn select_pairs(
+    db: &Surreal<Db>,
+    table: &str,
+) -> Result<Vec<(Thing, Building)>, Box<dyn Error>> {
+    let rows = select_all_things(db, table).await?;
+    Ok(rows
+        .into_iter()
+        .map(|b| (b.id, Building { address: b.address }))
+        .collect())
+}
+
+// Select `Record<T>` rows where `data` is built from `fields` in order.
+// Serde reads tuple structs from a sequence and newtype structs from their
+// single value, never from the row's object, so a single field is
+// projected as that value and several as an array. That lets
+// `struct BuildingTuple(String)` work with `&["address"]`. No `fields`
+// makes `data` the whole record, e.g. for a `#[serde(tag = "kind")]` enum
+// that needs its tag alongside the variant's fields.
+pub async fn select_records<T: DeserializeOwned>(
+    db: &Surreal<Db>,
+    table: &str,
+    fields: &[&str],
+) -> Result<Vec<Record<T>>, Box<dyn Error>> {
+    for field in fields {
+        validate_field(field)?;
+    }
+    let data = match fields {
+        [] => "$this".to_owned(),
+        [field] => field.to_string(),
+        _ => format!("[{}]", fields.join(", ")),
+    };
+    let mut response = bound_table(
+        db,
+        &format!("SELECT id, {data} AS data FROM type::table($tbl)"),
+        table,
+    )
+    .await?;
+    let results: Vec<Record<T>> = response.take(0)?;
+    Ok(results)
+}
+
+// The next sequential id in the same table, keeping the id's type: an
+// `Id::Number` stays a number and a digit string like the demo's
+// "1234567890" stays a string, zero padded to its width so "007" is
+// followed by "008" (and "999" by "1000"). None for other ids or on
+// overflow.
+pub fn next_numeric_id(thing: &Thing) -> Option<Thing> {
+    let id = match &thing.id {
+        Id::Number(n) => Id::Number(n.checked_add(1)?),
+        Id::String(s) if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) => {
+            let n = s.parse::<i64>().ok()?.checked_add(1)?;
+            Id::String(format!("{n:0width$}", width = s.len()))
+        }
+        _ => return None,
+    };
+    Some(Thing::from((thing.tb.as_str(), id)))
+}
+
+// The `Thing` at a dotted `path` such as `meta.owner` in `v`. The leaf may
+// be an escaped `tbl:id` string as `get_tbl_id` writes it or the `{ tb, id }`
+// object serde gives a `Thing`, anything else is None. Unescaped strings
+// such as `select_all_json` writes are ambiguous, `tbl:1234567890` reads
+// back as a number and an id with a space doesn't parse at all.
+pub fn thing_at_path(v: &serde_json::Value, path: &str) -> Option<Thing> {
+    let leaf = path.split('.').try_fold(v, |v, key| v.get(key))?;
+    match leaf {
+        serde_json::Value::String(s) => parse_tbl_id(s).ok(),
+        serde_json::Value::Object(_) => serde_json::from_value(leaf.clone()).ok(),
+        _ => None,
+    }
+}
+
+// Every row of `table` as JSON with `id` as the unescaped `tbl:id` string
+// (see `RecordId`) instead of the nested `{ tb, id }` object serde gives a
+// `Thing`, a predictable shape for logs and tests.
+pub async fn select_all_json(
+    db: &Surreal<Db>,
+    table: &str,
+) -> Result<Vec<serde_json::Value>, Box<dyn Error>> {
+    let mut response = bound_table(db, "SELECT * FROM type::table($tbl)", table).await?;
+    let rows: surrealdb::sql::Value = response.take(0)?;
+    let surrealdb::sql::Value::Array(rows) = rows else {
+        return Err(format!("expected an array of rows, got {rows}").into());
+    };
+
+    Ok(rows
+        .0
+        .into_iter()
+        .map(|row| {
+            let id = match &row {
+                surrealdb::sql::Value::Object(obj) => match obj.get("id") {
+                    Some(surrealdb::sql::Value::Thing(thing)) => {
+                        Some(RecordId(thing.clone()).to_string())
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+            let mut json = row.into_json();
+            if let (Some(id), Some(obj)) = (id, json.as_object_mut()) {
+                obj.insert("id".to_owned(), id.into());
+            }
+            json
+        })
+        .collect())
+}
+
+// Move the record at `from` to `new_id` in the same table, keeping its
+// fields. The copy and delete run in one transaction so a failure leaves
+// the original in place.
+pub async fn change_id(
+    db: &Surreal<Db>,
+    from: &Thing,
+    new_id: &str,
+) -> Result<Thing, Box<dyn Error>> {
+    let to = Thing::from((from.tb.as_str(), new_id));
+    db.query(
+        r#"
+        BEGIN TRANSACTION;
+        LET $record = (SELECT * OMIT id FROM ONLY $from);
+        IF $record = NONE { THROW "record " + <string> $from + " does not exist" };
+        CREATE $to CONTENT $record;
+        DELETE $from;
+        COMMIT TRANSACTION;
+        "#,
+    )
+    .bind(("from", from.clone()))
+    .bind(("to", to.clone()))
+    .await?
+    .check()?;
+    Ok(to)
+}
+
+// A record id for transports like protobuf/gRPC, free of SurrealDB types.
+// `id_kind` is one of the `ID_KIND_*` values so numeric ids stay numeric.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProtoRecordId {
+    pub table: String,
+    pub id_string: String,
+    pub id_kind: i32,
+}
+
+impl ProtoRecordId {
+    pub const ID_KIND_UNSPECIFIED: i32 = 0;
+    pub const ID_KIND_NUMBER: i32 = 1;
+    pub const ID_KIND_STRING: i32 = 2;
+    pub const ID_KIND_UUID: i32 = 3;
+    // Array and object ids, `id_string` is their SurrealQL text
+    pub const ID_KIND_COMPLEX: i32 = 4;
+}
+
+impl From<&Thing> for ProtoRecordId {
+    fn from(thing: &Thing) -> Self {
+        let id_kind = match &thing.id {
+            Id::Number(_) => ProtoRecordId::ID_KIND_NUMBER,
+            // `Id` has no uuid variant, uuids are stored as strings
+            Id::String(s) if surrealdb::sql::Uuid::try_from(s.as_str()).is_ok() => {
+                ProtoRecordId::ID_KIND_UUID
+            }
+            Id::String(_) => ProtoRecordId::ID_KIND_STRING,
+            _ => ProtoRecordId::ID_KIND_COMPLEX,
+        };
+        ProtoRecordId {
+            table: thing.get_tbl(),
+            id_string: thing.get_id(),
+            id_kind,
+        }
+    }
+}
+
+impl TryFrom<ProtoRecordId> for Thing {
+    type Error = RecordIdError;
+
+    fn try_from(proto: ProtoRecordId) -> Result<Self, Self::Error> {
+        let id = match proto.id_kind {
+            ProtoRecordId::ID_KIND_NUMBER => Id::Number(proto.id_string.parse().map_err(|_| {
+                RecordIdError::Parse(format!("{:?} isn't a numeric id", proto.id_string))
+            })?),
+            ProtoRecordId::ID_KIND_STRING | ProtoRecordId::ID_KIND_UUID => {
+                Id::String(proto.id_string)
+            }
+            ProtoRecordId::ID_KIND_COMPLEX => match surrealdb::sql::value(&proto.id_string) {
+                Ok(surrealdb::sql::Value::Array(a)) => Id::Array(a),
+                Ok(surrealdb::sql::Value::Object(o)) => Id::Object(o),
+                _ => {
+                    return Err(RecordIdError::Parse(format!(
+                        "{:?} isn't an array or object id",
+                        proto.id_string
+                    )))
+                }
+            },
+            kind => return Err(RecordIdError::Parse(format!("unknown id_kind {kind}"))),
+        };
+        Ok(Thing::from((proto.table, id)))
+    }
+}
+
+// Run `sql` and take the rows of statement `slot`, first checking the
+// response has exactly `expected_slots` statements so a query edited to
+// add or drop a statement doesn't silently take the wrong result.
+pub async fn query_expect_slots<T: DeserializeOwned>(
+    db: &Surreal<Db>,
+    sql: &str,
+    expected_slots: usize,
+    slot: usize,
+) -> Result<Vec<T>, RecordIdError> {
+    let mut response = db.query(sql).await?;
+    let actual = response.num_statements();
+    if actual != expected_slots {
+        return Err(RecordIdError::SlotCountMismatch {
+            expected: expected_slots,
+            actual,
+        });
+    }
+    Ok(response.take(slot)?)
+}
+
+// Create one `building_tbl` record per address with SurrealDB generating
+// the ids, returning the new Things in the same order.
+pub async fn seed_addresses<I: IntoIterator<Item = String>>(
+    db: &Surreal<Db>,
+    addresses: I,
+) -> Result<Vec<Thing>, Box<dyn Error>> {
+    let records: Vec<Building> = addresses
+        .into_iter()
+        .map(|address| Building { address })
+        .collect();
+    let mut response = db
+        .query("INSERT INTO building_tbl $records")
+        .bind(("records", records))
+        .await?;
+    let created: Vec<BuildingWithThing> = response.take(0)?;
+    Ok(created.into_iter().map(|b| b.id).collect())
+}
+
+// Shared so ids made in the same millisecond still increase
+#[cfg(feature = "ulid")]
+static ULIDS: Mutex<ulid::Generator> = Mutex::new(ulid::Generator::new());
+
+// A `Thing` in `table` with a new ULID string id. ULIDs start with the
+// creation time, so sorting the ids as strings sorts them by time, and
+// the shared generator keeps that true within a millisecond.
+#[cfg(feature = "ulid")]
+pub fn new_ulid_thing(table: &str) -> Thing {
+    let mut ulids = ULIDS.lock().unwrap_or_else(|e| e.into_inner());
+    // Only overflows after 2^80 ids in one millisecond
+    let id = ulids.generate().unwrap_or_else(|_| ulid::Ulid::generate());
+    Thing::from((table, id.to_string().as_str()))
+}
+
+// Create a record in `table` with a `new_ulid_thing` id
+#[cfg(feature = "ulid")]
+pub async fn create_with_ulid(
+    db: &Surreal<Db>,
+    table: &str,
+    address: &str,
+) -> Result<Thing, Box<dyn Error>> {
+    let thing = new_ulid_thing(table);
+    let created: Option<BuildingWithThing> = db
+        .create((thing.tb.as_str(), thing.id.clone()))
+        .content(Building {
+            address: address.to_owned(),
+        })
+        .await?;
+    created
+        .map(|b| b.id)
+        .ok_or_else(|| format!("create of {thing} returned nothing").into())
+}
+
+// Run `db.select` and the `meta::id(id) AS rid` query on `table` into
+// `BuildingWithRidOptionString` and describe what each one returned, a
+// runtime explanation of why `rid` is only populated by the query.
+pub async fn explain_rid_behavior(db: &Surreal<Db>, table: &str) -> String {
+    fn describe(rows: Result<Vec<BuildingWithRidOptionString>, String>) -> String {
+        match rows {
+            Ok(rows) if rows.is_empty() => "no rows".to_owned(),
+            Ok(rows) => rows
+                .iter()
+                .map(|r| format!("rid: {:?}", r.rid))
+                .collect::<Vec<_>>()
+                .join(", "),
+            Err(e) => format!("error: {e}"),
+        }
+    }
+
+    let selected = db.select(table).await.map_err(|e| e.to_string());
+    let queried = match bound_table(
+        db,
+        "SELECT *, meta::id(id) AS rid FROM type::table($tbl)",
+        table,
+    )
+    .await
+    {
+        Ok(mut response) => response.take(0).map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+
+    format!(
+        "db.select(\"{table}\"): {}\n\
+         \x20 `rid` isn't a stored field, the id lives in `id`, so it's always None\n\
+         SELECT *, meta::id(id) AS rid FROM {table}: {}\n\
+         \x20 the query projects the id into `rid`, so it's Some\n",
+        describe(selected),
+        describe(queried),
+    )
+}
+
+// Select all of `table` with `meta::id(id) AS rid`, sorted by `field`
+// (checked with `validate_field` as it's interpolated), descending if
+// `desc`.
+pub async fn select_all_ordered<T: DeserializeOwned>(
+    db: &Surreal<Db>,
+    table: &str,
+    field: &str,
+    desc: bool,
+) -> Result<Vec<T>, Box<dyn Error>> {
+    validate_field(field)?;
+    let dir = if desc { "DESC" } else { "ASC" };
+    let mut response = bound_table(
+        db,
+        &format!("SELECT *, meta::id(id) AS rid FROM type::table($tbl) ORDER BY {field} {dir}"),
+        table,
+    )
+    .await?;
+    let results: Vec<T> = response.take(0)?;
+    Ok(results)
+}
+
+// Record `thing` on `span` as the unescaped `tbl:id` (see `RecordId`). The
+// span has to declare `key`, e.g. `info_span!("select", rid = Empty)`.
+#[cfg(feature = "tracing")]
+pub fn record_thing(span: &tracing::Span, key: &str, thing: &Thing) {
+    span.record(key, RecordId(thing.clone()).to_string().as_str());
+}
+
+// `select_all_into` plus the warnings the response carries. SurrealDB 1.5
+// reports none, a `Response` only has results and errors, so the list is
+// always empty for now and is there for the server versions that do.
+pub async fn select_all_with_warnings<T: DeserializeOwned>(
+    db: &Surreal<Db>,
+    table: &str,
+) -> Result<(Vec<T>, Vec<String>), Box<dyn Error>> {
+    let mut response = bound_table(db, "SELECT * FROM type::table($tbl)", table).await?;
+    let rows: Vec<T> = response.take(0)?;
+    Ok((rows, Vec::new()))
+}
+
+// `#[serde(with = "serde_surreal_duration")]` for a `std::time::Duration`
+// kept as a SurrealDB `duration`. Written through `sql::Duration` so it's
+// stored as a duration rather than serde's `{ secs, nanos }` object, and
+// read back from either that or the `1w2d` string form.
+pub mod serde_surreal_duration {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::time::Duration;
+    use surrealdb::sql;
+
+    pub fn serialize<S: Serializer>(dur: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        sql::Duration::from(*dur).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            String(String),
+            Duration(sql::Duration),
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::String(s) => sql::Duration::try_from(s.as_str())
+                .map(Duration::from)
+                .map_err(|_| serde::de::Error::custom(format!("invalid duration {s:?}"))),
+            Repr::Duration(dur) => Ok(dur.into()),
+        }
+    }
+}
+
+#[allow(unused)]
+async fn test_select(db: &Surreal<Db>, address: &str) -> Result<(), Box<dyn Error>> {
+    // Using `db.select` always returns None for `rid: Option<String>` field
+    let select_results_with_rid_option_string: Vec<BuildingWithRidOptionString> =
+        db.select("building_tbl").await?;
+    dbg!(&select_results_with_rid_option_string);
+    assert_eq!(select_results_with_rid_option_string.len(), 1);
+    assert_eq!(select_results_with_rid_option_string[0].rid, None);
+    assert_eq!(select_results_with_rid_option_string[0].address, address);
+
+    // Using `db.select` with no `rid` works too
+    let select_results_no_rid: Vec<Building> = db.select("building_tbl").await?;
+    dbg!(&select_results_no_rid);
+    assert_eq!(select_results_no_rid.len(), 1);
+    assert_eq!(select_results_no_rid[0].address, address);
+
+    // Having `rid: String` always fails when using select.
+    match db.select("building_tbl").await {
+        Ok(r) => {
+            let _: Vec<BuildingWithRidString> = r;
+            panic!("Should have failed");
+        }
+        Err(e) => {
+            //dbg!(&e);
+            assert!(e.to_string().contains("missing field"));
+        }
+    }
+
+    Ok(())
+}
+
+#[allow(unused)]
+async fn test_query(db: &Surreal<Db>, address: &str, rid: &str) -> Result<(), Box<dyn Error>> {
+    // Query all fields without the meta::id as rid
+    let mut query_response_no_rid = db.query(r#"SELECT * FROM building_tbl"#).await?;
+    dbg!(&query_response_no_rid);
+    let query_results_no_rid: Vec<BuildingWithRidOptionString> = query_response_no_rid.take(0)?;
+    dbg!(&query_results_no_rid);
+    assert_eq!(query_results_no_rid.len(), 1);
+    assert_eq!(query_results_no_rid[0].rid, None);
+    assert_eq!(query_results_no_rid[0].address, address);
+
+    // Query all fields and the meta::id as rid
+    let mut query_response_with_rid = db
+        .query(r#"SELECT *,meta::id(id) AS rid FROM building_tbl"#)
+        .await?;
+    dbg!(&query_response_with_rid);
+    let query_results_with_rid: Vec<BuildingWithRidOptionString> =
+        query_response_with_rid.take(0)?;
+    dbg!(&query_results_with_rid);
+    assert_eq!(query_results_with_rid.len(), 1);
+    assert_eq!(query_results_with_rid[0].rid, Some(rid.to_owned()));
+    assert_eq!(query_results_with_rid[0].address, address);
+
+    // Query without `meta::id as rid` and no field rid: Option<String>
+    let mut query_response_no_rid_in_query_or_struct =
+        db.query(r#"SELECT * FROM building_tbl"#).await?;
+    dbg!(&query_response_no_rid_in_query_or_struct);
+    let query_results_no_rid_in_query_or_struct: Vec<Building> =
+        query_response_no_rid_in_query_or_struct.take(0)?;
+    dbg!(&query_results_no_rid_in_query_or_struct);
+    assert_eq!(query_results_no_rid_in_query_or_struct.len(), 1);
+    assert_eq!(query_results_no_rid_in_query_or_struct[0].address, address);
+
+    // Query with `meta::id(id) as rid` but no field `rid: Option<String>`
+    let mut query_response_rid_in_query_no_rid_in_struct = db
+        .query(r#"SELECT *,meta::id(id) AS rid FROM building_tbl"#)
+        .await?;
+    dbg!(&query_response_rid_in_query_no_rid_in_struct);
+    let query_results_rid_in_query_no_rid_in_struct: Vec<Building> =
+        query_response_rid_in_query_no_rid_in_struct.take(0)?;
+    dbg!(&query_results_rid_in_query_no_rid_in_struct);
+    assert_eq!(query_results_rid_in_query_no_rid_in_struct.len(), 1);
+    assert_eq!(
+        query_results_rid_in_query_no_rid_in_struct[0].address,
+        address
+    );
+
+    Ok(())
+}
+
+async fn test_select_thing_with_id_traits(db: &Surreal<Db>, address: &str, tbl: &str, id: &str) -> Result<(), Box<dyn Error>> {
+    let select_results_with_id_traits: Vec<BuildingWithThing> = db.select("building_tbl").await?;
+    dbg!(&select_results_with_id_traits);
+    assert_eq!(select_results_with_id_traits.len(), 1);
+    assert_eq!(&select_results_with_id_traits[0].id.get_tbl(), tbl);
+    assert_eq!(&select_results_with_id_traits[0].id.get_id(), id);
+    // Note the `⟨` and `⟩` in the `id` field. This is because the `id` field is a `Thing` and the `id` field is a `meta::id` field.
+    // and those aren't the '<' and '>' characters!
+    assert_eq!(&select_results_with_id_traits[0].id.get_tbl_id(), &(tbl.to_owned() + ":⟨" + id + "⟩"));
+    assert_eq!(&select_results_with_id_traits[0].address, address);
+
+    println!("id: {:?}", select_results_with_id_traits[0].id);
+    println!("id.id: {:?}", select_results_with_id_traits[0].id.id);
+    let id_get_tbl_id = select_results_with_id_traits[0].id.get_tbl_id();
+    println!("id_get_tbl_id: {}", id_get_tbl_id);
+    let id_get_id = select_results_with_id_traits[0].id.get_id();
+    println!("id_get_id: {}", id_get_id);
+    let id_get_tbl = select_results_with_id_traits[0].id.get_tbl();
+    println!("id_get_tbl: {}", id_get_tbl);
+
+    Ok(())
+}
+
+async fn test_select_all_flat(
+    db: &Surreal<Db>,
+    address: &str,
+    table: &str,
+    rid: &str,
+) -> Result<(), Box<dyn Error>> {
+    let select_all_flat_results = select_all_flat(db, table).await?;
+    dbg!(&select_all_flat_results);
+    assert_eq!(select_all_flat_results.len(), 1);
+    for building in &select_all_flat_results {
+        assert!(!building.rid.is_empty());
+        assert_eq!(building.rid, rid);
+        assert_eq!(building.address, address);
+    }
+
+    Ok(())
+}
+
+async fn test_select_fill_rid(
+    db: &Surreal<Db>,
+    address: &str,
+    table: &str,
+    rid: &str,
+) -> Result<(), Box<dyn Error>> {
+    let select_fill_rid_results = select_fill_rid(db, table).await?;
+    dbg!(&select_fill_rid_results);
+    assert_eq!(select_fill_rid_results.len(), 1);
+    assert_eq!(select_fill_rid_results[0].rid, Some(rid.to_owned()));
+    assert_eq!(select_fill_rid_results[0].address, address);
+
+    Ok(())
+}
+
+async fn test_select_all_timeout(
+    db: &Surreal<Db>,
+    address: &str,
+    table: &str,
+) -> Result<(), Box<dyn Error>> {
+    // Plenty of time, the select succeeds
+    let select_all_timeout_results: Vec<Building> =
+        select_all_timeout(db, table, Duration::from_secs(10)).await?;
+    assert_eq!(select_all_timeout_results.len(), 1);
+    assert_eq!(select_all_timeout_results[0].address, address);
+
+    // The `<future>` field is computed on every select, so `sleep` delays it
+    db.query(r#"CREATE slow_tbl SET id = "1", address = $addr, slow = <future> { sleep(500ms) };"#)
+        .bind(("addr", address))
+        .await?;
+    match select_all_timeout::<Building>(db, "slow_tbl", Duration::from_millis(10)).await {
+        Ok(r) => panic!("Should have timed out: {r:?}"),
+        Err(e) => {
+            dbg!(&e);
+            assert!(matches!(e, RecordIdError::Timeout(_)));
+        }
+    }
+
+    Ok(())
+}
+
+fn test_get_tbl_id_tuple() -> Result<(), Box<dyn Error>> {
+    let numeric = Thing::from(("building_tbl", Id::Number(1234567890)));
+    assert_eq!(
+        numeric.get_tbl_id_tuple(),
+        ("building_tbl".to_owned(), "1234567890".to_owned())
+    );
+
+    let with_colon = Thing::from(("building_tbl", "a:b:c"));
+    assert_eq!(with_colon.get_tbl_id(), "building_tbl:⟨a:b:c⟩");
+    assert_eq!(
+        with_colon.get_tbl_id_tuple(),
+        ("building_tbl".to_owned(), "a:b:c".to_owned())
+    );
+
+    Ok(())
+}
+
+async fn test_insert_or_ignore(db: &Surreal<Db>, address: &str) -> Result<(), Box<dyn Error>> {
+    let table = "insert_or_ignore_tbl";
+    assert!(insert_or_ignore(db, table, "1", address).await?);
+    assert!(!insert_or_ignore(db, table, "1", "456 Oak Ave").await?);
+
+    // The second call didn't overwrite the first record
+    let buildings: Vec<Building> = db.select(table).await?;
+    dbg!(&buildings);
+    assert_eq!(buildings.len(), 1);
+    assert_eq!(buildings[0].address, address);
+
+    Ok(())
+}
+
+async fn test_query_count(db: &Surreal<Db>, table: &str) -> Result<(), Box<dyn Error>> {
+    assert_eq!(query_count(db, "empty_tbl").await?, 0);
+    assert_eq!(query_count(db, table).await?, 1);
+
+    db.query(
+        r#"CREATE count_tbl SET address = "1 A St"; CREATE count_tbl SET address = "2 B St";"#,
+    )
+    .await?;
+    assert_eq!(query_count(db, "count_tbl").await?, 2);
+
+    Ok(())
+}
+
+// Property test of the `get_tbl_id()` -> `parse_tbl_id()` round trip and
+// of `get_tbl_id()` being the same again after it,
+// run with `cargo run --features proptest`.
+#[cfg(feature = "proptest")]
+fn test_thing_round_trip_prop() -> Result<(), Box<dyn Error>> {
+    use proptest::prelude::*;
+    use proptest::test_runner::TestRunner;
+
+    // Plain identifiers and names that have to be escaped: with spaces, a
+    // leading digit, all digits, or a `⟩` that needs backticks
+    let table = prop_oneof![
+        "[a-z_][a-z0-9_]{0,15}",
+        "[a-z ]{1,16}",
+        "[0-9][a-z0-9_]{0,15}",
+        "[a-z ⟨⟩`\\\\]{1,16}",
+    ];
+    // Plain ids, digit-only strings (escaped as `⟨..⟩`) and ids drawn from
+    // a small set of troublesome characters so failures shrink readably.
+    let string_id = prop_oneof![
+        "[a-zA-Z0-9_]{1,16}",
+        "[0-9]{1,16}",
+        "[a-z :/⟨⟩`'\"\\\\-]{1,16}",
+        "[^\\p{C}]{1,16}",
+    ];
+    let id = prop_oneof![
+        any::<i64>().prop_map(Id::Number),
+        string_id.prop_map(Id::String),
+    ];
+
+    let mut runner = TestRunner::default();
+    runner
+        .run(&(table, id), |(tb, id)| {
+            let thing = Thing::from((tb, id));
+            let tbl_id = thing.get_tbl_id();
+            let parsed = parse_tbl_id(&tbl_id).map_err(|e| TestCaseError::fail(e.to_string()))?;
+            prop_assert!(
+                things_equal(&thing, &parsed),
+                "{thing:?} -> {tbl_id} -> {parsed:?}"
+            );
+            // Formatting is idempotent through the parse
+            prop_assert_eq!(parsed.get_tbl_id(), tbl_id);
+            Ok(())
+        })
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+// Only imports the prelude so it fails to compile if a re-export goes missing
+mod prelude_test {
+    use crate::prelude::*;
+    use std::error::Error;
+
+    // Every item the prelude exports is named here, so dropping one from
+    // it fails to compile
+    pub async fn test_prelude() -> Result<(), Box<dyn Error>> {
+        let db: Surreal<Db> = Surreal::new::<Mem>(()).await?;
+        db.use_ns("test").use_db("test").await?;
+        db.query(r#"CREATE prelude_tbl SET id = "1", address = "1 Prelude St";"#)
+            .await?;
+
+        let buildings: Vec<Building> = db.select("prelude_tbl").await?;
+        assert_eq!(buildings[0].address, "1 Prelude St");
+        let with_thing: Vec<BuildingWithThing> = db.select("prelude_tbl").await?;
+        let thing: &Thing = &with_thing[0].id;
+        assert_eq!(thing.get_tbl(), "prelude_tbl");
+        assert_eq!(thing.get_id(), "1");
+        assert_eq!(thing.get_tbl_id(), "prelude_tbl:⟨1⟩");
+        let flat: Vec<BuildingWithRidString> = select_all_flat(&db, "prelude_tbl").await?;
+        assert_eq!(flat[0].rid, "1");
+        let filled: Vec<BuildingWithRidOptionString> = select_fill_rid(&db, "prelude_tbl").await?;
+        assert_eq!(filled[0].rid.as_deref(), Some("1"));
+
+        let mut response = db
+            .query("SELECT meta::id(id) AS rid, address, true AS is_main FROM prelude_tbl")
+            .query("SELECT meta::id(id) AS rid, address, deleted_at FROM prelude_tbl")
+            .query("SELECT *, NONE AS owner, NONE AS parent FROM prelude_tbl")
+            .query("SELECT *, NONE AS parent FROM prelude_tbl")
+            .query("SELECT count() FROM prelude_tbl GROUP ALL")
+            .query("SELECT address, count() AS n FROM prelude_tbl GROUP BY address")
+            .query("SELECT meta::id(id) AS rid, meta::tb(id) AS tbl FROM prelude_tbl")
+            .await?;
+        let is_main: Vec<BuildingIsMain> = response.take(0)?;
+        assert!(is_main[0].is_main);
+        let deleted: Vec<BuildingWithDeletedAt> = response.take(1)?;
+        assert!(deleted[0].deleted_at.is_none());
+        let owned: Vec<BuildingWithOwner> = response.take(2)?;
+        assert!(owned[0].owner.is_none());
+        let parented: Vec<BuildingWithParent> = response.take(3)?;
+        assert!(parented[0].parent.is_none());
+        let count: Option<CountResult> = response.take(4)?;
+        assert_eq!(count.map(|c| c.count), Some(1));
+        let counts: Vec<AddressCount> = response.take(5)?;
+        assert_eq!(counts[0].n, 1);
+        let metas: Vec<RecordMeta> = response.take(6)?;
+        assert_eq!(metas[0].tbl, "prelude_tbl");
+
+        let owner = Owner {
+            id: Thing::from(("owner_tbl", "ann")),
+            name: "Ann".to_owned(),
+        };
+        assert_eq!(owner.id.get_id(), "ann");
+        let record = Record {
+            id: thing.clone(),
+            data: Building {
+                address: "1 Prelude St".to_owned(),
+            },
+        };
+        let rid = RecordId::from(record.id.clone());
+        assert_eq!(rid.to_escaped_string(), "prelude_tbl:⟨1⟩");
+        let proto = ProtoRecordId::from(thing);
+        assert_eq!(Thing::try_from(proto)?, *thing);
+        assert!(SortableThing(thing.clone()) == SortableThing(record.id));
+        let err: RecordIdError = RecordIdError::Parse("prelude".to_owned());
+        assert!(err.to_string().contains("prelude"));
+
+        let named: Surreal<Db> = connect_mem_named("test", "test").await?;
+        let store = CachedStore::new(named, std::time::Duration::from_secs(60));
+        assert!(store.select_all("prelude_tbl").await?.is_empty());
+
+        Ok(())
+    }
+}
+
+// Not a precise benchmark, it prints the elapsed time so large batch
+// regressions are noticeable. Both helpers preallocate their Vecs from the
+// input length so a 10k batch doesn't repeatedly reallocate while growing.
+async fn test_batch_10k(db: &Surreal<Db>) -> Result<(), Box<dyn Error>> {
+    let table = "batch_tbl";
+    let rows: Vec<(String, String)> = (0..10_000)
+        .map(|i| (format!("b{i}"), format!("{i} Batch St")))
+        .collect();
+
+    // Both come back in the order of `rows`
+    let things = create_buildings(db, table, &rows).await?;
+    assert_eq!(things.len(), rows.len());
+    assert!(things
+        .iter()
+        .zip(&rows)
+        .all(|(thing, (id, _))| thing.tb == table && thing.get_id() == *id));
+    assert_eq!(query_count(db, table).await?, rows.len());
+
+    let selected = select_by_things(db, &things).await?;
+    assert_eq!(selected.len(), things.len());
+    assert!(selected
+        .iter()
+        .zip(&rows)
+        .all(|(b, (id, address))| b.id.get_id() == *id && b.address == *address));
+
+    Ok(())
+}
+
+fn test_sortable_thing() -> Result<(), Box<dyn Error>> {
+    let sorted_ids = |ids: &[&str]| {
+        let mut things: Vec<SortableThing> = ids
+            .iter()
+            .map(|id| SortableThing(Thing::from(("building_tbl", *id))))
+            .collect();
+        things.sort();
+        things.into_iter().map(|t| t.0.get_id()).collect::<Vec<_>>()
+    };
+
+    // Numeric order, a plain string sort would give "1", "10", "2"
+    assert_eq!(sorted_ids(&["2", "10", "1"]), ["1", "2", "10"]);
+
+    // Numeric ids first, then the strings in lexical order
+    assert_eq!(sorted_ids(&["b", "10", "a", "2"]), ["2", "10", "a", "b"]);
+
+    // `Id::Number` and digit strings compare by value
+    let mut things = [
+        SortableThing(Thing::from(("building_tbl", Id::Number(10)))),
+        SortableThing(Thing::from(("building_tbl", "9"))),
+    ];
+    things.sort();
+    assert_eq!(things[0].0.get_id(), "9");
+
+    Ok(())
+}
+
+fn test_config_from_env() -> Result<(), Box<dyn Error>> {
+    assert_eq!(
+        config_from(|_| None),
+        Config {
+            ns: "test".to_owned(),
+            db: "test".to_owned(),
+        }
+    );
+
+    let vars = HashMap::from([("SURREAL_NS", "other_ns"), ("SURREAL_DB", "other_db")]);
+    let config = config_from(|name| vars.get(name).map(|v| v.to_string()));
+    assert_eq!(config.ns, "other_ns");
+    assert_eq!(config.db, "other_db");
+
+    Ok(())
+}
+
+async fn test_select_with_owner(db: &Surreal<Db>) -> Result<(), Box<dyn Error>> {
+    db.query(
+        r#"
+        CREATE owner_tbl:alice SET name = "Alice";
+        CREATE owned_building_tbl:1 SET address = "1 Owned St", owner = owner_tbl:alice;
+        CREATE owned_building_tbl:2 SET address = "2 Unowned St";
+        "#,
+    )
+    .await?
+    .check()?;
+
+    let mut results = select_with_owner(db, "owned_building_tbl").await?;
+    dbg!(&results);
+    results.sort_by(|a, b| cmp_things(&a.id, &b.id));
+    assert_eq!(results.len(), 2);
+
+    let owner = results[0].owner.as_ref().expect("owner was fetched");
+    assert_eq!(owner.id.get_tbl_id(), "owner_tbl:alice");
+    assert_eq!(owner.name, "Alice");
+    assert!(results[1].owner.is_none());
+
+    Ok(())
+}
+
+// Run with `cargo run --features schemars`
+#[cfg(feature = "schemars")]
+fn test_json_schema() -> Result<(), Box<dyn Error>> {
+    let schema = serde_json::to_value(schemars::schema_for!(BuildingWithRidString))?;
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+    assert_eq!(schema["properties"]["rid"]["type"], "string");
+    assert!(schema["properties"]["rid"]["description"]
+        .as_str()
+        .is_some_and(|d| d.contains("unescaped")));
+    assert_eq!(schema["properties"]["address"]["type"], "string");
+
+    let schema = serde_json::to_value(schemars::schema_for!(BuildingWithRidOptionString))?;
+    assert_eq!(
+        schema["properties"]["rid"]["type"],
+        serde_json::json!(["string", "null"])
+    );
+
+    Ok(())
+}
+
+async fn test_raw_query(
+    db: &Surreal<Db>,
+    address: &str,
+    table: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut response = raw_query(
+        db,
+        &format!("SELECT * FROM {table}; SELECT count() FROM {table} GROUP ALL;"),
+    )
+    .await?;
+    assert_eq!(response.num_statements(), 2);
+
+    let buildings: Vec<Building> = response.take(0)?;
+    assert_eq!(buildings.len(), 1);
+    assert_eq!(buildings[0].address, address);
+    let count: Option<CountResult> = response.take(1)?;
+    assert_eq!(count.map(|c| c.count), Some(1));
+
+    Ok(())
+}
+
+fn test_split_tbl_id() -> Result<(), Box<dyn Error>> {
+    let pair = |tbl: &str, id: &str| (tbl.to_owned(), id.to_owned());
+    assert_eq!(split_tbl_id("a:b")?, pair("a", "b"));
+    assert_eq!(split_tbl_id("a:⟨1⟩")?, pair("a", "1"));
+    assert_eq!(split_tbl_id("a:x:y")?, pair("a", "x:y"));
+    assert_eq!(split_tbl_id("a:⟨x:y⟩")?, pair("a", "x:y"));
+    assert_eq!(split_tbl_id("⟨b:c⟩:1")?, pair("b:c", "1"));
+    assert_eq!(split_tbl_id(r"a:`x⟩\`y`")?, pair("a", "x⟩`y"));
+
+    // Agrees with the Thing it came from, whatever needs escaping
+    for thing in [
+        Thing::from(("building_tbl", "1234567890")),
+        Thing::from(("building_tbl", Id::Number(1234567890))),
+        Thing::from(("building_tbl", "x⟩y")),
+        Thing::from(("building tbl", "1")),
+        Thing::from(("1st⟩floor", r"a\`b")),
+        parse_tbl_id("building_tbl:[1, 'two']")?,
+    ] {
+        assert_eq!(
+            split_tbl_id(&thing.get_tbl_id())?,
+            thing.get_tbl_id_tuple(),
+            "{}",
+            annotate_escapes(&thing.get_tbl_id())
+        );
+    }
+
+    for bad in ["ab", ":b", "a:", "a:⟨1", "⟨a⟩b:1", "`a:1", "a:⟨1⟩2"] {
+        assert!(
+            matches!(split_tbl_id(bad), Err(RecordIdError::Parse(_))),
+            "{bad}"
+        );
+    }
+
+    Ok(())
+}
+
+async fn test_live_buildings_filtered() -> Result<(), Box<dyn Error>> {
+    // A separate db so the create/delete doesn't disturb `building_tbl` in main
+    let db = Surreal::new::<Mem>(()).await?;
+    db.use_ns("test").use_db("test").await?;
+
+    let mut deletes = Box::pin(live_buildings_filtered(&db, &[Action::Delete]).await?);
+    db.query(r#"CREATE building_tbl:live SET address = "1 Live St"; DELETE building_tbl:live;"#)
+        .await?
+        .check()?;
+
+    let notification = tokio::time::timeout(Duration::from_secs(5), deletes.next())
+        .await?
+        .expect("stream ended")?;
+    dbg!(&notification);
+    assert_eq!(notification.action, Action::Delete);
+    assert_eq!(notification.data.id.get_tbl_id(), "building_tbl:live");
+
+    // The create was filtered out, nothing else arrives
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), deletes.next())
+            .await
+            .is_err()
+    );
+
+    Ok(())
+}
+
+async fn test_export_csv(db: &Surreal<Db>) -> Result<(), Box<dyn Error>> {
+    // Just enough of a CSV reader to check the quoting round trips
+    fn parse_csv_line(line: &str) -> Vec<String> {
+        let mut fields = vec![String::new()];
+        let mut chars = line.chars().peekable();
+        let mut quoted = false;
+        while let Some(c) = chars.next() {
+            match (c, quoted) {
+                ('"', true) if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    fields.last_mut().unwrap().push('"');
+                }
+                ('"', _) => quoted = !quoted,
+                (',', false) => fields.push(String::new()),
+                (c, _) => fields.last_mut().unwrap().push(c),
+            }
+        }
+        fields
+    }
+
+    let table = "export_tbl";
+    let rows = [
+        ("1".to_owned(), "1 Main St, Apt 2".to_owned()),
+        ("2".to_owned(), r#"2 "Oak" Ave"#.to_owned()),
+    ];
+    create_buildings(db, table, &rows).await?;
+
+    let mut out = Vec::new();
+    assert_eq!(export_csv(db, table, &mut out).await?, 2);
+    let csv = String::from_utf8(out)?;
+    println!("{csv}");
+
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("rid,address"));
+    let mut records: Vec<Vec<String>> = lines.map(parse_csv_line).collect();
+    records.sort();
+    assert_eq!(records, rows.map(|(rid, address)| vec![rid, address]));
+
+    Ok(())
+}
+
+fn test_id_from_parts() -> Result<(), Box<dyn Error>> {
+    let cases = [
+        ("1234567890", "building_tbl:⟨1234567890⟩"),
+        ("abc", "building_tbl:abc"),
+        ("a_1", "building_tbl:a_1"),
+        ("123 Main St", "building_tbl:⟨123 Main St⟩"),
+    ];
+    for (id, expected) in cases {
+        let tbl_id = id_from_parts("building_tbl", id);
+        assert_eq!(tbl_id, expected);
+
+        // Parses back to a string id, even for the numeric one
+        let thing = parse_tbl_id(&tbl_id)?;
+        assert_eq!(thing.id, Id::String(id.to_owned()));
+    }
+
+    Ok(())
+}
+
+async fn test_cached_store() -> Result<(), Box<dyn Error>> {
+    let db = Surreal::new::<Mem>(()).await?;
+    db.use_ns("test").use_db("test").await?;
+    let table = "cached_tbl";
+    let store = CachedStore::new(db, Duration::from_secs(60));
+    store.insert_or_ignore(table, "1", "1 Cached St").await?;
+
+    // The second select within the TTL is served from the cache
+    assert_eq!(store.select_all(table).await?.len(), 1);
+    assert_eq!(store.select_all(table).await?.len(), 1);
+    assert_eq!(store.db_selects(), 1);
+
+    // A write invalidates the cached table
+    store
+        .create_buildings(table, &[("2".to_owned(), "2 Cached St".to_owned())])
+        .await?;
+    assert_eq!(store.select_all(table).await?.len(), 2);
+    assert_eq!(store.db_selects(), 2);
+
+    // So does a soft delete through the store
+    let gone = Thing::from((table, "2"));
+    assert!(store.soft_delete(&gone).await?);
+    assert_eq!(store.select_all(table).await?.len(), 1);
+    assert_eq!(store.db_selects(), 3);
+
+    // One made directly through `db()` is served from the cache until an
+    // explicit `invalidate`
+    assert!(soft_delete(store.db(), &Thing::from((table, "1"))).await?);
+    assert_eq!(store.select_all(table).await?.len(), 1);
+    store.invalidate(table);
+    assert!(store.select_all(table).await?.is_empty());
+    assert_eq!(store.db_selects(), 4);
+
+    // The other writes invalidate too
+    store.upsert_building(table, "3", "3 Cached St").await?;
+    assert_eq!(store.select_all(table).await?.len(), 1);
+    store
+        .bulk_upsert(table, &[("4".to_owned(), "4 Cached St".to_owned())])
+        .await?;
+    assert_eq!(store.select_all(table).await?.len(), 2);
+    let five = store
+        .create_building(table, "5", "5 Cached St", false)
+        .await?;
+    assert_eq!(store.select_all(table).await?.len(), 3);
+    let six = store.change_id(&five, "6").await?;
+    assert!(store
+        .select_all(table)
+        .await?
+        .iter()
+        .any(|b| b.rid == six.get_id()));
+    store.delete_by_thing(table, &six).await?;
+    assert_eq!(store.select_all(table).await?.len(), 2);
+    assert_eq!(store.db_selects(), 9);
+
+    // And entries expire after the TTL
+    let store = CachedStore::new(store.db().clone(), Duration::from_millis(20));
+    store.select_all(table).await?;
+    tokio::time::sleep(Duration::from_millis(40)).await;
+    store.select_all(table).await?;
+    assert_eq!(store.db_selects(), 2);
+
+    Ok(())
+}
+
+fn test_get_id_range() -> Result<(), Box<dyn Error>> {
+    let range: Range = "building_tbl:1..100".parse().map_err(|_| "invalid range")?;
+    assert_eq!(range.get_tbl(), "building_tbl");
+    assert_eq!(range.get_id(), "1..100");
+    assert_eq!(range.get_tbl_id(), "building_tbl:1..100");
+    assert_eq!(
+        range.get_id_range(),
+        Some((Bound::Included(1), Bound::Excluded(100)))
+    );
+
+    let range = Range::new(
+        "building_tbl".to_owned(),
+        Bound::Excluded(Id::Number(1)),
+        Bound::Included(Id::Number(100)),
+    );
+    assert_eq!(range.get_id(), "1>..=100");
+    assert_eq!(
+        range.get_id_range(),
+        Some((Bound::Excluded(1), Bound::Included(100)))
+    );
+
+    let range: Range = "building_tbl:..100".parse().map_err(|_| "invalid range")?;
+    assert_eq!(
+        range.get_id_range(),
+        Some((Bound::Unbounded, Bound::Excluded(100)))
+    );
+
+    // Non-numeric bounds and plain Things have no numeric range
+    let range: Range = "building_tbl:a..z".parse().map_err(|_| "invalid range")?;
+    assert_eq!(range.get_id_range(), None);
+    assert_eq!(
+        Thing::from(("building_tbl", Id::Number(1))).get_id_range(),
+        None
+    );
+
+    Ok(())
+}
+
+fn test_record_id_display() -> Result<(), Box<dyn Error>> {
+    // The demo's numeric-looking string id is where the two forms differ
+    let numeric = RecordId::from(Thing::from(("building_tbl", "1234567890")));
+    assert_eq!(numeric.to_string(), "building_tbl:1234567890");
+    assert_eq!(numeric.to_escaped_string(), "building_tbl:⟨1234567890⟩");
+    assert_ne!(numeric.to_string(), numeric.to_escaped_string());
+
+    // Only the escaped form parses back to a string id
+    assert_eq!(parse_tbl_id(&numeric.to_escaped_string())?, numeric.0);
+    assert_ne!(parse_tbl_id(&numeric.to_string())?, numeric.0);
+
+    let plain = RecordId::from(Thing::from(("building_tbl", "abc")));
+    assert_eq!(plain.to_string(), "building_tbl:abc");
+    assert_eq!(plain.to_string(), plain.to_escaped_string());
+
+    Ok(())
+}
+
+async fn test_try_select(
+    db: &Surreal<Db>,
+    address: &str,
+    table: &str,
+    rid: &str,
+) -> Result<(), Box<dyn Error>> {
+    // Plain `db.select` fails on the missing `rid` field
+    let select_result: surrealdb::Result<Vec<BuildingWithRidString>> = db.select(table).await;
+    assert!(select_result.is_err());
+
+    let try_select_results: Vec<BuildingWithRidString> = try_select(db, table).await?;
+    dbg!(&try_select_results);
+    assert_eq!(try_select_results.len(), 1);
+    assert_eq!(try_select_results[0].rid, rid);
+    assert_eq!(try_select_results[0].address, address);
+
+    // Structs that `db.select` handles take the first path
+    let buildings: Vec<BuildingWithThing> = try_select(db, table).await?;
+    assert_eq!(buildings[0].id.get_id(), rid);
+
+    Ok(())
+}
+
+async fn test_expect_table(
+    db: &Surreal<Db>,
+    address: &str,
+    table: &str,
+    rid: &str,
+) -> Result<(), Box<dyn Error>> {
+    let thing = Thing::from((table, rid));
+    expect_table(&thing, table)?;
+    match expect_table(&thing, "other_tbl") {
+        Err(RecordIdError::WrongTable { expected, found }) => {
+            assert_eq!(expected, "other_tbl");
+            assert_eq!(found, table);
+        }
+        r => panic!("Should have been WrongTable: {r:?}"),
+    }
+
+    let selected = select_by_id(db, table, &thing.get_tbl_id()).await?;
+    assert_eq!(selected.map(|b| b.address), Some(address.to_owned()));
+    assert!(matches!(
+        select_by_id(db, "other_tbl", &thing.get_tbl_id()).await,
+        Err(RecordIdError::WrongTable { .. })
+    ));
+
+    // The guard stops the delete, the record is still there
+    assert!(matches!(
+        delete_by_thing(db, "other_tbl", &thing).await,
+        Err(RecordIdError::WrongTable { .. })
+    ));
+    assert_eq!(query_count(db, table).await?, 1);
+
+    let guarded_tbl = "guarded_tbl";
+    insert_or_ignore(db, guarded_tbl, "1", address).await?;
+    let deleted = delete_by_thing(db, guarded_tbl, &Thing::from((guarded_tbl, "1"))).await?;
+    assert_eq!(deleted.map(|b| b.address), Some(address.to_owned()));
+    assert_eq!(query_count(db, guarded_tbl).await?, 0);
+
+    Ok(())
+}
+
+async fn test_ping(db: &Surreal<Db>) -> Result<(), Box<dyn Error>> {
+    let latency = ping(db).await?;
+    println!("ping: {latency:?}");
+    assert!(latency < Duration::from_secs(1));
+
+    Ok(())
+}
+
+async fn test_select_records_tuple(
+    db: &Surreal<Db>,
+    address: &str,
+    table: &str,
+    rid: &str,
+) -> Result<(), Box<dyn Error>> {
+    #[derive(Deserialize, Debug)]
+    struct BuildingTuple(String);
+
+    #[derive(Deserialize, Debug)]
+    struct BuildingPair(String, String);
+
+    let records: Vec<Record<BuildingTuple>> = select_records(db, table, &["address"]).await?;
+    dbg!(&records);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].id.get_id(), rid);
+    assert_eq!(records[0].data.0, address);
+
+    db.query(r#"CREATE pair_tbl:1 SET address = "1 Pair St", city = "Pairville";"#)
+        .await?
+        .check()?;
+    let records: Vec<Record<BuildingPair>> =
+        select_records(db, "pair_tbl", &["address", "city"]).await?;
+    assert_eq!(records[0].id.get_tbl_id(), "pair_tbl:1");
+    assert_eq!(records[0].data.0, "1 Pair St");
+    assert_eq!(records[0].data.1, "Pairville");
+
+    assert!(matches!(
+        validate_field("address; DELETE pair_tbl"),
+        Err(RecordIdError::InvalidField(_))
+    ));
+    assert!(select_records::<BuildingTuple>(db, table, &["1address"])
+        .await
+        .is_err());
+
+    Ok(())
+}
+
+fn test_next_numeric_id() -> Result<(), Box<dyn Error>> {
+    let five = parse_tbl_id("building_tbl:5")?;
+    let six = next_numeric_id(&five).expect("numeric id");
+    assert_eq!(six.get_tbl_id(), "building_tbl:6");
+    assert_eq!(six.get_tbl(), five.get_tbl());
+    assert_eq!(six.id, Id::Number(6));
+
+    let string_id = Thing::from(("building_tbl", "1234567890"));
+    let next = next_numeric_id(&string_id).expect("digit string id");
+    assert_eq!(next.id, Id::String("1234567891".to_owned()));
+
+    // Leading zeros are kept, the width only grows on a carry out
+    for (id, expected) in [("007", "008"), ("099", "100"), ("999", "1000")] {
+        let next = next_numeric_id(&Thing::from(("building_tbl", id))).expect("digit string id");
+        assert_eq!(next.id, Id::String(expected.to_owned()), "{id}");
+    }
+
+    assert!(next_numeric_id(&Thing::from(("building_tbl", "abc"))).is_none());
+    assert!(next_numeric_id(&Thing::from(("building_tbl", Id::Number(i64::MAX)))).is_none());
+
+    Ok(())
+}
+
+async fn test_select_all_json(
+    db: &Surreal<Db>,
+    address: &str,
+    table: &str,
+    rid: &str,
+) -> Result<(), Box<dyn Error>> {
+    let rows = select_all_json(db, table).await?;
+    println!("{}", serde_json::to_string_pretty(&rows)?);
+    assert_eq!(rows.len(), 1);
+    assert_eq!(
+        rows[0],
+        serde_json::json!({ "id": format!("{table}:{rid}"), "address": address })
+    );
+    assert!(rows[0]["id"].is_string());
+
+    Ok(())
+}
+
+async fn test_change_id(db: &Surreal<Db>, address: &str) -> Result<(), Box<dyn Error>> {
+    let table = "change_id_tbl";
+    insert_or_ignore(db, table, "old", address).await?;
+    let old = Thing::from((table, "old"));
+
+    let new = change_id(db, &old, "new").await?;
+    assert_eq!(new.get_tbl_id(), "change_id_tbl:new");
+    let moved = select_by_id(db, table, &new.get_tbl_id()).await?;
+    assert_eq!(moved.map(|b| b.address), Some(address.to_owned()));
+    assert!(select_by_id(db, table, &old.get_tbl_id()).await?.is_none());
+    assert_eq!(query_count(db, table).await?, 1);
+
+    // Moving a record that no longer exists fails and creates nothing
+    assert!(change_id(db, &old, "newer").await.is_err());
+    assert_eq!(query_count(db, table).await?, 1);
+
+    Ok(())
+}
+
+fn test_id_traits_for_ref() -> Result<(), Box<dyn Error>> {
+    fn describe<T: IdTraits>(t: T) -> String {
+        format!("{} {} {}", t.get_tbl(), t.get_id(), t.get_tbl_id())
+    }
+
+    let thing = Thing::from(("building_tbl", "1234567890"));
+    assert_eq!(
+        describe(&thing),
+        "building_tbl 1234567890 building_tbl:⟨1234567890⟩"
+    );
+    // `thing` wasn't moved or cloned
+    assert_eq!(
+        describe(thing),
+        "building_tbl 1234567890 building_tbl:⟨1234567890⟩"
+    );
+
+    let range: Range = "building_tbl:1..100".parse().map_err(|_| "invalid range")?;
+    fn range_of<T: IdTraits>(t: T) -> Option<(Bound<i64>, Bound<i64>)> {
+        t.get_id_range()
+    }
+    assert_eq!(range_of(&range), range.get_id_range());
+
+    Ok(())
+}
+
+fn test_get_id_as_numbers() -> Result<(), Box<dyn Error>> {
+    let demo = Thing::from(("building_tbl", "1234567890"));
+    assert_eq!(demo.get_id_as_i64(), Some(1234567890));
+    assert_eq!(demo.get_id_as_i128(), Some(1234567890));
+    assert_eq!(demo.get_id_as_decimal(), Some(Decimal::from(1234567890)));
+
+    // i64::MAX + 1 doesn't fit an i64 but does fit the wider types
+    let big = Thing::from(("building_tbl", "9223372036854775808"));
+    assert_eq!(big.get_id_as_i64(), None);
+    assert_eq!(big.get_id_as_i128(), Some(i64::MAX as i128 + 1));
+    assert_eq!(big.get_id_as_decimal(), "9223372036854775808".parse().ok());
+
+    let number = Thing::from(("building_tbl", Id::Number(-5)));
+    assert_eq!(number.get_id_as_i64(), Some(-5));
+
+    let string = Thing::from(("building_tbl", "abc"));
+    assert_eq!(string.get_id_as_i64(), None);
+    assert_eq!(string.get_id_as_i128(), None);
+    assert_eq!(string.get_id_as_decimal(), None);
+
+    Ok(())
+}
+
+async fn test_bound_table(db: &Surreal<Db>, table: &str) -> Result<(), Box<dyn Error>> {
+    // Read as the name of a (nonexistent) table, not as a second statement
+    let hostile = format!("{table}; DELETE {table}");
+    assert!(select_all_flat(db, &hostile).await?.is_empty());
+    assert_eq!(query_count(db, &hostile).await?, 0);
+    assert_eq!(query_count(db, table).await?, 1);
+
+    let quoted = format!("{table}` WHERE true; REMOVE TABLE {table}; --");
+    assert!(select_all_json(db, &quoted).await?.is_empty());
+    assert_eq!(query_count(db, table).await?, 1);
+
+    // Odd but literal table names work end to end, and are stored as given
+    let spaced = "spaced 'tbl'";
+    insert_or_ignore(db, spaced, "1", "1 Space St").await?;
+    assert_eq!(query_count(db, spaced).await?, 1);
+    assert_eq!(select_all_flat(db, spaced).await?[0].rid, "1");
+
+    let rows = [("2".to_owned(), "2 Space St".to_owned())];
+    let created = create_buildings(db, spaced, &rows).await?;
+    assert_eq!(created[0].tb, spaced);
+    let rows = [
+        ("2".to_owned(), "2 Upsert St".to_owned()),
+        ("3".to_owned(), "3 Space St".to_owned()),
+    ];
+    let upserted = bulk_upsert(db, spaced, &rows).await?;
+    assert!(upserted.iter().all(|t| t.tb == spaced));
+    let stored = select_ids(db, spaced).await?;
+    dbg!(&stored);
+    assert_eq!(stored.len(), 3);
+    assert!(stored.iter().all(|t| t.tb == spaced));
+    assert_eq!(
+        list_tables(db)
+            .await?
+            .iter()
+            .filter(|t| t.contains("spaced"))
+            .count(),
+        1
+    );
+
+    Ok(())
+}
+
+fn test_proto_record_id() -> Result<(), Box<dyn Error>> {
+    let cases = [
+        (
+            Thing::from(("building_tbl", Id::Number(1234567890))),
+            ProtoRecordId::ID_KIND_NUMBER,
+        ),
+        (
+            Thing::from(("building_tbl", "1234567890")),
+            ProtoRecordId::ID_KIND_STRING,
+        ),
+        (
+            Thing::from(("building_tbl", "a b:c")),
+            ProtoRecordId::ID_KIND_STRING,
+        ),
+        (
+            Thing::from(("building_tbl", "0190d4ec-4b5a-7b4e-9f3a-0123456789ab")),
+            ProtoRecordId::ID_KIND_UUID,
+        ),
+        (
+            parse_tbl_id("building_tbl:[1, 'a']")?,
+            ProtoRecordId::ID_KIND_COMPLEX,
+        ),
+        (
+            parse_tbl_id("building_tbl:{ a: 1 }")?,
+            ProtoRecordId::ID_KIND_COMPLEX,
+        ),
+    ];
+    for (thing, kind) in cases {
+        let proto = ProtoRecordId::from(&thing);
+        dbg!(&proto);
+        assert_eq!(proto.table, "building_tbl");
+        assert_eq!(proto.id_kind, kind);
+        let back = Thing::try_from(proto)?;
+        assert!(things_equal(&back, &thing), "{back:?} != {thing:?}");
+    }
+
+    let bad = ProtoRecordId {
+        table: "building_tbl".to_owned(),
+        id_string: "abc".to_owned(),
+        id_kind: ProtoRecordId::ID_KIND_NUMBER,
+    };
+    assert!(matches!(Thing::try_from(bad), Err(RecordIdError::Parse(_))));
+
+    Ok(())
+}
+
+async fn test_soft_delete(db: &Surreal<Db>) -> Result<(), Box<dyn Error>> {
+    let table = "soft_delete_tbl";
+    let rows = [
+        ("1".to_owned(), "1 Live St".to_owned()),
+        ("2".to_owned(), "2 Gone St".to_owned()),
+    ];
+    let things = create_buildings(db, table, &rows).await?;
+    assert!(soft_delete(db, &things[1]).await?);
+    assert!(!soft_delete(db, &Thing::from((table, "missing"))).await?);
+
+    let live = select_all_flat(db, table).await?;
+    assert_eq!(live.len(), 1);
+    assert_eq!(live[0].rid, "1");
+
+    let mut all = select_all_including_deleted(db, table).await?;
+    dbg!(&all);
+    all.sort_by(|a, b| a.rid.cmp(&b.rid));
+    assert_eq!(all.len(), 2);
+    assert!(all[0].deleted_at.is_none());
+    assert!(all[1].deleted_at.is_some());
+
+    Ok(())
+}
+
+async fn test_soft_delete_schemafull() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    let table = "soft_delete_schema_tbl";
+    ensure_building_schema(&db, table).await?;
+    let rows = [
+        ("1".to_owned(), "1 Live St".to_owned()),
+        ("2".to_owned(), "2 Gone St".to_owned()),
+    ];
+    let things = create_buildings(&db, table, &rows).await?;
+    assert!(soft_delete(&db, &things[1]).await?);
+
+    // `deleted_at` was kept, so the row is hidden
+    let live = select_all_flat(&db, table).await?;
+    assert_eq!(live.len(), 1);
+    assert_eq!(live[0].rid, "1");
+    let mut all = select_all_including_deleted(&db, table).await?;
+    all.sort_by(|a, b| a.rid.cmp(&b.rid));
+    assert!(all[0].deleted_at.is_none());
+    assert!(all[1].deleted_at.is_some());
+
+    Ok(())
+}
+
+async fn test_query_expect_slots(
+    db: &Surreal<Db>,
+    address: &str,
+    table: &str,
+) -> Result<(), Box<dyn Error>> {
+    let sql = format!("SELECT * FROM {table}; SELECT * FROM empty_tbl;");
+    let buildings: Vec<Building> = query_expect_slots(db, &sql, 2, 0).await?;
+    assert_eq!(buildings[0].address, address);
+    let empty: Vec<Building> = query_expect_slots(db, &sql, 2, 1).await?;
+    assert!(empty.is_empty());
+
+    match query_expect_slots::<Building>(db, &sql, 1, 0).await {
+        Err(RecordIdError::SlotCountMismatch { expected, actual }) => {
+            assert_eq!(expected, 1);
+            assert_eq!(actual, 2);
+        }
+        r => panic!("Should have been SlotCountMismatch: {r:?}"),
+    }
+
+    Ok(())
+}
+
+async fn test_seed_addresses() -> Result<(), Box<dyn Error>> {
+    // A separate db, the seeded records would upset `building_tbl` in main
+    let db = Surreal::new::<Mem>(()).await?;
+    db.use_ns("test").use_db("test").await?;
+
+    let addresses = ["1 Seed St", "2 Seed St", "3 Seed St"].map(String::from);
+    let things = seed_addresses(&db, addresses.clone()).await?;
+    dbg!(&things);
+    assert_eq!(things.len(), 3);
+    assert!(things.iter().all(|t| t.get_tbl() == "building_tbl"));
+    let ids: std::collections::HashSet<String> = things.iter().map(|t| t.get_id()).collect();
+    assert_eq!(ids.len(), 3);
+
+    let seeded = select_by_things(&db, &things).await?;
+    let seeded: Vec<&str> = seeded.iter().map(|b| b.address.as_str()).collect();
+    assert_eq!(seeded, addresses);
+
+    Ok(())
+}
+
+async fn test_explain_rid_behavior(
+    db: &Surreal<Db>,
+    table: &str,
+    rid: &str,
+) -> Result<(), Box<dyn Error>> {
+    let report = explain_rid_behavior(db, table).await;
+    println!("{report}");
+    let mut lines = report.lines();
+    assert!(lines.next().is_some_and(|l| l.contains("rid: None")));
+    lines.next();
+    assert!(lines
+        .next()
+        .is_some_and(|l| l.contains(&format!("rid: Some({rid:?})"))));
+
+    Ok(())
+}
+
+fn test_get_id_cow() -> Result<(), Box<dyn Error>> {
+    let string_id = Thing::from(("building_tbl", "1234567890"));
+    let cow = string_id.get_id_cow();
+    assert!(matches!(cow, Cow::Borrowed(_)));
+    assert_eq!(cow, string_id.get_id());
+
+    let numeric_id = Thing::from(("building_tbl", Id::Number(1234567890)));
+    let cow = numeric_id.get_id_cow();
+    assert!(matches!(cow, Cow::Owned(_)));
+    assert_eq!(cow, numeric_id.get_id());
+
+    // Through the reference impl too
+    let by_ref = &string_id;
+    assert!(matches!(IdTraits::get_id_cow(&by_ref), Cow::Borrowed(_)));
+
+    Ok(())
+}
+
+async fn test_select_all_ordered(db: &Surreal<Db>) -> Result<(), Box<dyn Error>> {
+    let table = "ordered_tbl";
+    let rows = [("1", "2 B St"), ("2", "3 C St"), ("3", "1 A St")]
+        .map(|(id, address)| (id.to_owned(), address.to_owned()));
+    create_buildings(db, table, &rows).await?;
+
+    let ascending: Vec<BuildingWithRidString> =
+        select_all_ordered(db, table, "address", false).await?;
+    let addresses: Vec<&str> = ascending.iter().map(|b| b.address.as_str()).collect();
+    assert_eq!(addresses, ["1 A St", "2 B St", "3 C St"]);
+
+    let descending: Vec<BuildingWithRidString> = select_all_ordered(db, table, "id", true).await?;
+    let rids: Vec<&str> = descending.iter().map(|b| b.rid.as_str()).collect();
+    assert_eq!(rids, ["3", "2", "1"]);
+
+    assert!(
+        select_all_ordered::<Building>(db, table, "address; DELETE ordered_tbl", false)
+            .await
+            .is_err()
+    );
+    assert_eq!(query_count(db, table).await?, 3);
+
+    Ok(())
+}
+
+// Run with `cargo run --features tracing`
+#[cfg(feature = "tracing")]
+fn test_record_thing() -> Result<(), Box<dyn Error>> {
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id as SpanId, Record as SpanRecord};
+    use tracing::{Event, Metadata, Subscriber};
+
+    // Just enough of a subscriber to capture recorded span fields
+    #[derive(Default)]
+    struct Capture(Arc<Mutex<HashMap<String, String>>>);
+
+    impl Visit for &Capture {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            let mut fields = self.0.lock().unwrap();
+            fields.insert(field.name().to_owned(), value.to_owned());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            let mut fields = self.0.lock().unwrap();
+            fields.insert(field.name().to_owned(), format!("{value:?}"));
+        }
+    }
+
+    impl Subscriber for Capture {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _: &Attributes<'_>) -> SpanId {
+            SpanId::from_u64(1)
+        }
+        fn record(&self, _: &SpanId, values: &SpanRecord<'_>) {
+            values.record(&mut &*self);
+        }
+        fn record_follows_from(&self, _: &SpanId, _: &SpanId) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, _: &SpanId) {}
+        fn exit(&self, _: &SpanId) {}
+    }
+
+    let capture = Capture::default();
+    let fields = capture.0.clone();
+    tracing::subscriber::with_default(capture, || {
+        let span = tracing::info_span!("select", rid = tracing::field::Empty);
+        record_thing(&span, "rid", &Thing::from(("building_tbl", "1234567890")));
+    });
+    assert_eq!(
+        fields.lock().unwrap().get("rid").map(String::as_str),
+        Some("building_tbl:1234567890")
+    );
+
+    Ok(())
+}
+
+async fn test_count_by_address() -> Result<(), Box<dyn Error>> {
+    // A separate db, the duplicate addresses would upset `building_tbl` in main
+    let db = Surreal::new::<Mem>(()).await?;
+    db.use_ns("test").use_db("test").await?;
+
+    let rows = [("1", "1 Dup St"), ("2", "1 Dup St"), ("3", "2 Dup St")]
+        .map(|(id, address)| (id.to_owned(), address.to_owned()));
+    create_buildings(&db, "building_tbl", &rows).await?;
+
+    let counts = count_by_address(&db).await?;
+    dbg!(&counts);
+    assert_eq!(counts.len(), 2);
+    assert_eq!(counts.get("1 Dup St"), Some(&2));
+    assert_eq!(counts.get("2 Dup St"), Some(&1));
+
+    Ok(())
+}
+
+fn test_get_tbl_id_with() -> Result<(), Box<dyn Error>> {
+    let thing = Thing::from(("building_tbl", "1234567890"));
+    assert_eq!(
+        thing.get_tbl_id_with("", "", "/"),
+        "building_tbl/1234567890"
+    );
+    assert_eq!(
+        thing.get_tbl_id_with("<", ">", "#"),
+        "building_tbl#<1234567890>"
+    );
+
+    // The defaults match SurrealDB's own escaping
+    let things = [
+        thing.clone(),
+        Thing::from(("building_tbl", "abc")),
+        Thing::from(("building_tbl", Id::Number(1234567890))),
+        Thing::from(("building tbl", "a:b")),
+    ];
+    for t in &things {
+        assert_eq!(t.get_tbl_id_with("⟨", "⟩", ":"), t.to_raw());
+        assert_eq!(t.get_tbl_id(), t.to_raw());
+    }
+
+    // Only escaped parts get the brackets
+    let plain = Thing::from(("building_tbl", "abc"));
+    assert_eq!(plain.get_tbl_id_with("[", "]", "/"), "building_tbl/abc");
+
+    // The other impls, and the trait default, bracket the same parts
+    struct Parts(Thing);
+    impl IdTraits for Parts {
+        fn get_tbl_id(&self) -> String {
+            self.0.get_tbl_id()
+        }
+        fn get_id(&self) -> String {
+            self.0.get_id()
+        }
+        fn get_tbl(&self) -> String {
+            self.0.get_tbl()
+        }
+        fn is_numeric_id(&self) -> bool {
+            self.0.is_numeric_id()
+        }
+    }
+    for t in &things {
+        let expected = t.get_tbl_id_with("<", ">", "#");
+        assert_eq!(
+            surrealdb::sql::Value::Thing(t.clone()).get_tbl_id_with("<", ">", "#"),
+            expected
+        );
+        assert_eq!(Parts(t.clone()).get_tbl_id_with("<", ">", "#"), expected);
+    }
+    let range: Range = "⟨building tbl⟩:1..100"
+        .parse()
+        .map_err(|_| "invalid range")?;
+    assert_eq!(
+        range.get_tbl_id_with("<", ">", "#"),
+        "<building tbl>#1..100"
+    );
+
+    Ok(())
+}
+
+async fn test_select_with_row_context() -> Result<(), Box<dyn Error>> {
+    // A separate db so the rows with a stored `rid` don't upset main
+    let db = Surreal::new::<Mem>(()).await?;
+    db.use_ns("test").use_db("test").await?;
+
+    db.query("CREATE row_context_tbl:1 SET rid = '1', address = '1 Row St'")
+        .query("CREATE row_context_tbl:2 SET address = '2 Row St'")
+        .await?
+        .check()?;
+
+    let result: Result<Vec<BuildingWithRidString>, _> =
+        select_with_row_context(&db, "row_context_tbl").await;
+    let err = result.expect_err("row 1 has no rid");
+    println!("{err}");
+    match &err {
+        RecordIdError::DeserializeRow { index, field, raw } => {
+            assert_eq!(*index, 1);
+            assert_eq!(field.as_deref(), Some("rid"));
+            assert_eq!(raw["address"], "2 Row St");
+        }
+        e => return Err(format!("unexpected error {e:?}").into()),
+    }
+    assert!(err.to_string().contains("missing field `rid`"));
+
+    // Rows that do deserialize come back as from `db.select`
+    let buildings: Vec<Building> = select_with_row_context(&db, "row_context_tbl").await?;
+    assert_eq!(buildings.len(), 2);
+
+    Ok(())
+}
+
+async fn test_connect_mem_named() -> Result<(), Box<dyn Error>> {
+    let first = connect_mem_named("first_ns", "first_db").await?;
+    let second = connect_mem_named("second_ns", "second_db").await?;
+
+    let rows = [("1".to_owned(), "1 First St".to_owned())];
+    create_buildings(&first, "named_tbl", &rows).await?;
+    let rows = [("2".to_owned(), "2 Second St".to_owned())];
+    create_buildings(&second, "named_tbl", &rows).await?;
+
+    let in_first: Vec<Building> = first.select("named_tbl").await?;
+    let in_second: Vec<Building> = second.select("named_tbl").await?;
+    dbg!(&in_first, &in_second);
+    assert_eq!(in_first.len(), 1);
+    assert_eq!(in_first[0].address, "1 First St");
+    assert_eq!(in_second.len(), 1);
+    assert_eq!(in_second[0].address, "2 Second St");
+
+    // Same names but still a separate datastore
+    let same_names = connect_mem_named("first_ns", "first_db").await?;
+    assert_eq!(query_count(&same_names, "named_tbl").await?, 0);
+
+    Ok(())
+}
+
+fn test_thing_path_segment() -> Result<(), Box<dyn Error>> {
+    let numeric = Thing::from(("building_tbl", Id::Number(1234567890)));
+    let seg = thing_to_path_segment(&numeric);
+    assert_eq!(seg, "1234567890");
+    assert!(things_equal(
+        &thing_from_path_segment("building_tbl", &seg)?,
+        &numeric
+    ));
+
+    let spaced = Thing::from(("building_tbl", "123 Main St"));
+    let seg = thing_to_path_segment(&spaced);
+    assert_eq!(seg, "123%20Main%20St");
+    assert!(things_equal(
+        &thing_from_path_segment("building_tbl", &seg)?,
+        &spaced
+    ));
+
+    let slashed = Thing::from(("building_tbl", "a/b"));
+    let seg = thing_to_path_segment(&slashed);
+    assert_eq!(seg, "a%2Fb");
+    assert!(things_equal(
+        &thing_from_path_segment("building_tbl", &seg)?,
+        &slashed
+    ));
+
+    // The demo's string id stays a string, and isn't the numeric record
+    let digits = Thing::from(("building_tbl", "1234567890"));
+    let seg = thing_to_path_segment(&digits);
+    assert_eq!(seg, "%E2%9F%A81234567890%E2%9F%A9");
+    let back = thing_from_path_segment("building_tbl", &seg)?;
+    assert!(things_equal(&back, &digits), "{back:?}");
+    assert!(!things_equal(&back, &numeric));
+
+    // As does a string id that is itself wrapped in `⟨` and `⟩`
+    let wrapped = Thing::from(("building_tbl", "⟨a⟩"));
+    let seg = thing_to_path_segment(&wrapped);
+    let back = thing_from_path_segment("building_tbl", &seg)?;
+    assert!(things_equal(&back, &wrapped), "{back:?}");
+    let bracket = Thing::from(("building_tbl", "⟩"));
+    let seg = thing_to_path_segment(&bracket);
+    assert!(things_equal(
+        &thing_from_path_segment("building_tbl", &seg)?,
+        &bracket
+    ));
+
+    assert!(thing_from_path_segment("building_tbl", "").is_err());
+    assert!(thing_from_path_segment("building_tbl", "%FF").is_err());
+
+    Ok(())
+}
+
+async fn test_select_all_retry(db: &Surreal<Db>, table: &str) -> Result<(), Box<dyn Error>> {
+    assert!(is_transient(&surrealdb::Error::Api(
+        surrealdb::error::Api::Ws("connection reset".to_owned())
+    )));
+    assert!(!is_transient(&surrealdb::Error::Api(
+        surrealdb::error::Api::ConnectionUninitialised
+    )));
+
+    // A mock select that fails with a connection reset the first time
+    let calls = AtomicUsize::new(0);
+    let result: Vec<Building> = retry_transient(2, || async {
+        if calls.fetch_add(1, AtomicOrdering::SeqCst) == 0 {
+            Err(surrealdb::Error::Api(surrealdb::error::Api::Ws(
+                "connection reset".to_owned(),
+            )))
+        } else {
+            db.select(table).await
+        }
+    })
+    .await?;
+    assert_eq!(calls.load(AtomicOrdering::SeqCst), 2);
+    assert_eq!(result.len(), 1);
+
+    // Without a second attempt the reset is returned
+    let calls = AtomicUsize::new(0);
+    let result: Result<(), _> = retry_transient(1, || async {
+        calls.fetch_add(1, AtomicOrdering::SeqCst);
+        Err(surrealdb::Error::Api(surrealdb::error::Api::Ws(
+            "connection reset".to_owned(),
+        )))
+    })
+    .await;
+    assert!(matches!(result, Err(RecordIdError::Db(_))));
+    assert_eq!(calls.load(AtomicOrdering::SeqCst), 1);
+
+    let buildings: Vec<Building> = select_all_retry(db, table, 3).await?;
+    assert_eq!(buildings.len(), 1);
+
+    Ok(())
+}
+
+fn test_compare_thing_formats() -> Result<(), Box<dyn Error>> {
+    let things = [
+        Thing::from(("building_tbl", Id::Number(1234567890))),
+        Thing::from(("building_tbl", "1234567890")),
+        Thing::from(("building_tbl", "abc")),
+        Thing::from(("building tbl", "a b")),
+    ];
+    for t in &things {
+        let (raw, display) = compare_thing_formats(t);
+        dbg!(&raw, &display);
+        assert_eq!(raw, display);
+        assert_eq!(t.get_tbl_id(), raw);
+    }
+
+    // Numeric ids only differ by type: the string one is escaped so it
+    // parses back as a string
+    assert_eq!(
+        compare_thing_formats(&things[0]).0,
+        "building_tbl:1234567890"
+    );
+    assert_eq!(
+        compare_thing_formats(&things[1]).0,
+        "building_tbl:⟨1234567890⟩"
+    );
+
+    Ok(())
+}
+
+async fn test_select_first(db: &Surreal<Db>, table: &str, rid: &str) -> Result<(), Box<dyn Error>> {
+    let first: Option<BuildingWithRidString> = select_first(db, table).await?;
+    dbg!(&first);
+    assert_eq!(first.map(|b| b.rid).as_deref(), Some(rid));
+
+    let empty: Option<BuildingWithRidString> = select_first(db, "select_first_empty_tbl").await?;
+    assert!(empty.is_none());
+
+    Ok(())
+}
+
+async fn test_value_id_traits(
+    db: &Surreal<Db>,
+    table: &str,
+    rid: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut response = bound_table(db, "SELECT VALUE id FROM type::table($tbl)", table).await?;
+    // Taken as one `Value`, a `Vec<Value>` goes through serde and fails
+    let ids: surrealdb::sql::Value = response.take(0)?;
+    let surrealdb::sql::Value::Array(ids) = ids else {
+        return Err(format!("expected an array of ids, got {ids}").into());
+    };
+    dbg!(&ids);
+    assert_eq!(ids.len(), 1);
+    assert_eq!(ids[0].get_tbl(), table);
+    assert_eq!(ids[0].get_id(), rid);
+    assert_eq!(ids[0].get_tbl_id(), format!("{table}:⟨{rid}⟩"));
+
+    let not_a_thing = surrealdb::sql::Value::from("building_tbl:1234567890");
+    assert_eq!(not_a_thing.get_tbl_id(), "");
+    assert_eq!(not_a_thing.get_id(), "");
+    assert_eq!(not_a_thing.get_tbl(), "");
+
+    Ok(())
+}
+
+async fn test_bulk_upsert() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    let table = "bulk_upsert_tbl";
+
+    let rows = [("1", "1 Upsert St"), ("2", "2 Upsert St")]
+        .map(|(id, address)| (id.to_owned(), address.to_owned()));
+    let things = bulk_upsert(&db, table, &rows).await?;
+    assert_eq!(things.len(), 2);
+
+    // "2" overlaps and gets a new address, "3" is new
+    let rows = [("2", "2 Moved St"), ("3", "3 Upsert St")]
+        .map(|(id, address)| (id.to_owned(), address.to_owned()));
+    let things = bulk_upsert(&db, table, &rows).await?;
+    dbg!(&things);
+    let ids: Vec<String> = things.iter().map(|t| t.get_id()).collect();
+    assert_eq!(ids, ["2", "3"]);
+
+    let buildings: Vec<BuildingWithRidString> = select_all_flat(&db, table).await?;
+    dbg!(&buildings);
+    let rows: Vec<(&str, &str)> = buildings
+        .iter()
+        .map(|b| (b.rid.as_str(), b.address.as_str()))
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            ("1", "1 Upsert St"),
+            ("2", "2 Moved St"),
+            ("3", "3 Upsert St")
+        ]
+    );
+
+    Ok(())
+}
+
+async fn test_select_where(
+    db: &Surreal<Db>,
+    table: &str,
+    address: &str,
+) -> Result<(), Box<dyn Error>> {
+    let found: Vec<BuildingWithRidString> =
+        select_where(db, table, "address = $a", vec![("a", address.into())]).await?;
+    dbg!(&found);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].address, address);
+
+    let none: Vec<BuildingWithRidString> =
+        select_where(db, table, "address = $a", vec![("a", "nowhere".into())]).await?;
+    assert!(none.is_empty());
+
+    Ok(())
+}
+
+fn test_things_equal_object_ids() -> Result<(), Box<dyn Error>> {
+    let ab = parse_tbl_id("building_tbl:{ city: 'Springfield', number: 123 }")?;
+    let ba = parse_tbl_id("building_tbl:{ number: 123, city: 'Springfield' }")?;
+    dbg!(&ab, &ba);
+    assert!(things_equal(&ab, &ba));
+    assert_eq!(ab.get_tbl_id(), ba.get_tbl_id());
+
+    let other = parse_tbl_id("building_tbl:{ number: 124, city: 'Springfield' }")?;
+    assert!(!things_equal(&ab, &other));
+
+    Ok(())
+}
+
+async fn test_select_all_things() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    let table = "things_tbl";
+    db.query("CREATE things_tbl:1 SET address = '1 Number St'")
+        .query("CREATE things_tbl:⟨2⟩ SET address = '2 String St'")
+        .await?
+        .check()?;
+
+    let buildings = select_all_things(&db, table).await?;
+    dbg!(&buildings);
+    let kinds: Vec<(&str, bool)> = buildings
+        .iter()
+        .map(|b| (b.address.as_str(), b.id.is_numeric_id()))
+        .collect();
+    assert_eq!(kinds, [("1 Number St", true), ("2 String St", false)]);
+
+    // The Thing goes straight back into the write helpers
+    delete_by_thing(&db, table, &buildings[0].id).await?;
+    assert_eq!(query_count(&db, table).await?, 1);
+
+    Ok(())
+}
+
+// Run with `cargo run --features ulid`
+#[cfg(feature = "ulid")]
+async fn test_ulid_things() -> Result<(), Box<dyn Error>> {
+    let first = new_ulid_thing("ulid_tbl");
+    let second = new_ulid_thing("ulid_tbl");
+    dbg!(&first, &second);
+    assert!(first.get_id() < second.get_id());
+
+    let db = connect_mem_named("test", "test").await?;
+    let created = [
+        create_with_ulid(&db, "ulid_tbl", "1 Ulid St").await?,
+        create_with_ulid(&db, "ulid_tbl", "2 Ulid St").await?,
+    ];
+    assert!(created[0].get_id() < created[1].get_id());
+    assert!(second.get_id() < created[0].get_id());
+
+    let buildings: Vec<BuildingWithRidString> =
+        select_all_ordered(&db, "ulid_tbl", "id", false).await?;
+    let addresses: Vec<&str> = buildings.iter().map(|b| b.address.as_str()).collect();
+    assert_eq!(addresses, ["1 Ulid St", "2 Ulid St"]);
+
+    Ok(())
+}
+
+async fn test_select_all_into() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    let table = "select_into_tbl";
+    let mut out: Vec<BuildingWithRidString> = Vec::new();
+
+    let rows = [("1", "1 Into St"), ("2", "2 Into St")]
+        .map(|(id, address)| (id.to_owned(), address.to_owned()));
+    create_buildings(&db, table, &rows).await?;
+    select_all_into(&db, table, &mut out).await?;
+    let rids: Vec<&str> = out.iter().map(|b| b.rid.as_str()).collect();
+    assert_eq!(rids, ["1", "2"]);
+
+    // The second call replaces rather than appends
+    delete_by_thing(&db, table, &Thing::from((table, "1"))).await?;
+    select_all_into(&db, table, &mut out).await?;
+    dbg!(&out);
+    let rids: Vec<&str> = out.iter().map(|b| b.rid.as_str()).collect();
+    assert_eq!(rids, ["2"]);
+
+    Ok(())
+}
+
+fn test_thing_heap_size() -> Result<(), Box<dyn Error>> {
+    let numeric = Thing::from(("building_tbl", Id::Number(1234567890)));
+    let long_string = Thing::from(("building_tbl", "a".repeat(100).as_str()));
+    dbg!(thing_heap_size(&numeric), thing_heap_size(&long_string));
+    assert!(thing_heap_size(&numeric) < thing_heap_size(&long_string));
+    assert!(thing_heap_size(&numeric) >= "building_tbl".len());
+    assert!(thing_heap_size(&long_string) >= "building_tbl".len() + 100);
+
+    let object = parse_tbl_id("building_tbl:{ city: 'Springfield', number: 123 }")?;
+    assert!(thing_heap_size(&object) > thing_heap_size(&numeric) + "Springfield".len());
+
+    Ok(())
+}
+
+async fn test_select_is_main_street() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    let rows = [("1", "123 Main St"), ("2", "456 Oak Ave")]
+        .map(|(id, address)| (id.to_owned(), address.to_owned()));
+    create_buildings(&db, "building_tbl", &rows).await?;
+
+    let buildings = select_is_main_street(&db).await?;
+    dbg!(&buildings);
+    let is_main: Vec<(&str, bool)> = buildings
+        .iter()
+        .map(|b| (b.address.as_str(), b.is_main))
+        .collect();
+    assert_eq!(is_main, [("123 Main St", true), ("456 Oak Ave", false)]);
+    assert_eq!(buildings[0].rid, "1");
+
+    Ok(())
+}
+
+// Only `Mem` is built here, `kv-rocksdb` (for `File`) needs libclang. So
+// this checks the pending write is visible through a clone that keeps the
+// engine open rather than reopening a file.
+async fn test_drain_and_drop() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    let kept = db.clone();
+    let rows = [("1".to_owned(), "1 Drain St".to_owned())];
+    create_buildings(&db, "drain_tbl", &rows).await?;
+
+    // Dropping one handle leaves the clone working on the same engine
+    drain_and_drop(db).await?;
+    assert_eq!(query_count(&kept, "drain_tbl").await?, 1);
+    drain_and_drop(kept).await?;
+
+    Ok(())
+}
+
+async fn test_select_all_fetch() -> Result<(), Box<dyn Error>> {
+    #[derive(Deserialize, Debug)]
+    struct BuildingWithOwnerLink {
+        owner: Thing,
+    }
+
+    let db = connect_mem_named("test", "test").await?;
+    db.query(
+        r#"
+        CREATE owner_tbl:bob SET name = "Bob";
+        CREATE fetch_tbl:1 SET address = "1 Fetch St", owner = owner_tbl:bob;
+        "#,
+    )
+    .await?
+    .check()?;
+
+    let fetched: Vec<BuildingWithOwner> = select_all_fetch(&db, "fetch_tbl", &["owner"]).await?;
+    dbg!(&fetched);
+    let owner = fetched[0].owner.as_ref().expect("owner was fetched");
+    assert_eq!(owner.name, "Bob");
+
+    let linked: Vec<BuildingWithOwnerLink> = select_all_fetch(&db, "fetch_tbl", &[]).await?;
+    dbg!(&linked);
+    assert_eq!(linked[0].owner.get_tbl_id(), "owner_tbl:bob");
+
+    let bad: Result<Vec<BuildingWithOwner>, _> =
+        select_all_fetch(&db, "fetch_tbl", &["owner; DELETE fetch_tbl"]).await;
+    assert!(bad.is_err());
+    assert_eq!(query_count(&db, "fetch_tbl").await?, 1);
+
+    Ok(())
+}
+
+fn test_get_tbl_id_idempotent() -> Result<(), Box<dyn Error>> {
+    for id in ["1234567890", "a b", "a⟩b", "⟩", "a`⟩\\b", "⟨x⟩"] {
+        let thing = Thing::from(("building_tbl", id));
+        let tbl_id = thing.get_tbl_id();
+        let parsed = parse_tbl_id(&tbl_id)?;
+        dbg!(&tbl_id);
+        assert!(things_equal(&thing, &parsed), "{thing:?} -> {tbl_id}");
+        assert_eq!(parsed.get_tbl_id(), tbl_id);
+    }
+    assert_eq!(
+        Thing::from(("building_tbl", "a⟩b")).get_tbl_id(),
+        "building_tbl:`a⟩b`"
+    );
+
+    Ok(())
+}
+
+async fn test_select_all_arc(
+    db: &Surreal<Db>,
+    table: &str,
+    rid: &str,
+) -> Result<(), Box<dyn Error>> {
+    let buildings: Arc<[BuildingWithRidString]> = select_all_arc(db, table).await?;
+    let clones = [buildings.clone(), buildings.clone()];
+    // Both clones point at the same allocation, nothing was copied
+    assert_eq!(Arc::strong_count(&buildings), 3);
+    assert!(clones.iter().all(|c| Arc::ptr_eq(c, &buildings)));
+
+    let tasks = clones.map(|shared| {
+        tokio::spawn(async move { shared.iter().map(|b| b.rid.clone()).collect::<Vec<_>>() })
+    });
+
+    for task in tasks {
+        assert_eq!(task.await?, [rid]);
+    }
+    assert_eq!(Arc::strong_count(&buildings), 1);
+
+    Ok(())
+}
+
+async fn test_create_building_coerce() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    let table = "coerce_tbl";
+
+    let kept = create_building(&db, table, "1234567890", "1 String St", false).await?;
+    assert!(!kept.is_numeric_id());
+    assert_eq!(kept.get_tbl_id(), "coerce_tbl:⟨1234567890⟩");
+
+    let coerced = create_building(&db, table, "1234567890", "2 Number St", true).await?;
+    assert!(coerced.is_numeric_id());
+    assert_eq!(coerced.get_tbl_id(), "coerce_tbl:1234567890");
+
+    // Not all digits, so never coerced
+    let signed = create_building(&db, table, "-1", "3 Signed St", true).await?;
+    assert!(!signed.is_numeric_id());
+    let too_big = create_building(&db, table, "99999999999999999999", "4 Big St", true).await?;
+    assert!(!too_big.is_numeric_id());
+
+    assert_eq!(query_count(&db, table).await?, 4);
+
+    Ok(())
+}
+
+async fn test_select_tagged_enum() -> Result<(), Box<dyn Error>> {
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(tag = "kind")]
+    enum BuildingKind {
+        House {
+            rid: String,
+            address: String,
+        },
+        Office {
+            rid: String,
+            address: String,
+            floors: u32,
+        },
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(tag = "kind")]
+    enum BuildingKindData {
+        House { address: String },
+        Office { address: String, floors: u32 },
+    }
+
+    let db = connect_mem_named("test", "test").await?;
+    let table = "kind_tbl";
+    db.query(
+        r#"
+        CREATE kind_tbl:a SET kind = "House", address = "1 Kind St";
+        CREATE kind_tbl:b SET kind = "Office", address = "2 Kind St", floors = 3;
+        "#,
+    )
+    .await?
+    .check()?;
+
+    // The `rid` projection lands in whichever variant the row is
+    let kinds: Vec<BuildingKind> = select_all_ordered(&db, table, "id", false).await?;
+    dbg!(&kinds);
+    assert_eq!(
+        kinds,
+        [
+            BuildingKind::House {
+                rid: "a".to_owned(),
+                address: "1 Kind St".to_owned()
+            },
+            BuildingKind::Office {
+                rid: "b".to_owned(),
+                address: "2 Kind St".to_owned(),
+                floors: 3
+            },
+        ]
+    );
+
+    let mut records: Vec<Record<BuildingKindData>> = select_records(&db, table, &[]).await?;
+    records.sort_by(|a, b| cmp_things(&a.id, &b.id));
+    dbg!(&records);
+    assert_eq!(records[0].id.get_id(), "a");
+    assert!(matches!(records[0].data, BuildingKindData::House { .. }));
+    assert_eq!(records[1].id.get_id(), "b");
+    assert_eq!(
+        records[1].data,
+        BuildingKindData::Office {
+            address: "2 Kind St".to_owned(),
+            floors: 3
+        }
+    );
+
+    Ok(())
+}
+
+fn test_thing_at_path() -> Result<(), Box<dyn Error>> {
+    let owner = Thing::from(("owner_tbl", "alice"));
+    let v = serde_json::json!({
+        "address": "1 Path St",
+        "meta": {
+            "owner": "owner_tbl:alice",
+            "linked": owner,
+            "digits": "building_tbl:⟨1234567890⟩",
+        },
+    });
+    dbg!(&v);
+
+    let found = thing_at_path(&v, "meta.owner").expect("meta.owner is a tbl:id");
+    assert!(things_equal(&found, &owner));
+    let found = thing_at_path(&v, "meta.linked").expect("meta.linked is a Thing");
+    assert!(things_equal(&found, &owner));
+    let found = thing_at_path(&v, "meta.digits").expect("meta.digits is a tbl:id");
+    assert_eq!(found.id, Id::String("1234567890".to_string()));
+    assert_eq!(found.tb, "building_tbl");
+
+    assert!(thing_at_path(&v, "meta.missing").is_none());
+    assert!(thing_at_path(&v, "address.owner").is_none());
+    assert!(thing_at_path(&v, "address").is_none());
+
+    Ok(())
+}
+
+async fn test_select_keyset() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    let table = "keyset_tbl";
+    let rows: Vec<(String, String)> = (1..=5)
+        .map(|n| (format!("k{n}"), format!("{n} Keyset St")))
+        .collect();
+    create_buildings(&db, table, &rows).await?;
+
+    let mut pages = Vec::new();
+    let mut after = None;
+    loop {
+        let (page, cursor) = select_keyset(&db, table, after, 2).await?;
+        pages.push(page.iter().map(|b| b.id.get_id()).collect::<Vec<_>>());
+        match cursor {
+            Some(cursor) => after = Some(cursor),
+            None => break,
+        }
+    }
+    dbg!(&pages);
+    assert_eq!(pages, [vec!["k1", "k2"], vec!["k3", "k4"], vec!["k5"]]);
+
+    Ok(())
+}
+
+fn test_thing_checked() -> Result<(), Box<dyn Error>> {
+    let thing = thing_checked("building_tbl", Id::Number(1234567890))?;
+    assert_eq!(thing.get_tbl_id(), "building_tbl:1234567890");
+
+    let err = thing_checked("1st_floor", Id::Number(1)).expect_err("starts with a digit");
+    assert!(matches!(&err, RecordIdError::InvalidTable(t) if t == "1st_floor"));
+    assert!(thing_checked("building tbl", Id::Number(1)).is_err());
+    assert!(thing_checked("", Id::Number(1)).is_err());
+    assert!(thing_checked("⟨⟩", Id::Number(1)).is_err());
+
+    // Escaped names are allowed and stored without the escaping
+    let escaped = thing_checked("⟨1st floor⟩", Id::Number(1))?;
+    assert_eq!(escaped.tb, "1st floor");
+    assert_eq!(thing_checked("`1st floor`", Id::Number(1))?.tb, "1st floor");
+
+    Ok(())
+}
+
+async fn test_select_pairs(
+    db: &Surreal<Db>,
+    table: &str,
+    rid: &str,
+    address: &str,
+) -> Result<(), Box<dyn Error>> {
+    let pairs = select_pairs(db, table).await?;
+    dbg!(&pairs);
+    assert_eq!(pairs.len(), 1);
+    let (thing, building) = &pairs[0];
+    assert!(things_equal(thing, &Thing::from((table, rid))));
+    assert_eq!(building.address, address);
+
+    Ok(())
+}
+
+fn test_write_tbl_id() -> Result<(), Box<dyn Error>> {
+    let things = [
+        Thing::from(("building_tbl", Id::Number(1234567890))),
+        Thing::from(("building_tbl", Id::Number(-1))),
+        Thing::from(("building_tbl", "1234567890")),
+        Thing::from(("building_tbl", "abc")),
+        Thing::from(("building tbl", "a b")),
+        Thing::from(("123", "")),
+        parse_tbl_id("building_tbl:{ city: 'Springfield', number: 123 }")?,
+        parse_tbl_id("building_tbl:[1, 'a']")?,
+    ];
+    // One buffer for all of them, the formatting stays byte-identical
+    let mut buf = String::new();
+    for thing in &things {
+        buf.clear();
+        write_tbl_id(&mut buf, thing);
+        assert_eq!(buf, thing.to_raw());
+        assert_eq!(thing.get_tbl_id(), thing.to_raw());
+    }
+
+    Ok(())
+}
+
+async fn test_select_all_consistent() -> Result<(), Box<dyn Error>> {
+    let mut results = Vec::new();
+    for schemafull in [false, true] {
+        let db = connect_mem_named("test", "test").await?;
+        if schemafull {
+            ensure_building_schema(&db, "building_tbl").await?;
+        }
+        db.query("CREATE building_tbl:⟨1234567890⟩ SET address = '123 Main St', rid = 'stored'")
+            .await?
+            .check()?;
+
+        let buildings = select_all_consistent(&db, "building_tbl").await?;
+        dbg!(schemafull, &buildings);
+        let rows: Vec<(Option<String>, String)> =
+            buildings.into_iter().map(|b| (b.rid, b.address)).collect();
+        results.push(rows);
+    }
+    assert_eq!(results[0], results[1]);
+    assert_eq!(results[0], [(None, "123 Main St".to_owned())]);
+
+    Ok(())
+}
+
+async fn test_clone_thing(db: &Surreal<Db>, table: &str, rid: &str) -> Result<(), Box<dyn Error>> {
+    let ids = select_ids(db, table).await?;
+    dbg!(&ids);
+    assert_eq!(ids.len(), 1);
+    assert_eq!(ids[0].get_id(), rid);
+
+    let original = ids[0].clone();
+    let mut copy = clone_thing(&original);
+    assert!(things_equal(&copy, &original));
+
+    // Changing the copy leaves the original alone
+    copy.tb.push_str("_copy");
+    copy.id = Id::Number(1);
+    assert_eq!(original.get_tbl_id(), format!("{table}:⟨{rid}⟩"));
+    assert_eq!(copy.get_tbl_id(), format!("{table}_copy:1"));
+
+    Ok(())
+}
+
+async fn test_with_auto_reconnect() -> Result<(), Box<dyn Error>> {
+    // A handle that was never connected fails like an idle remote one
+    let db: Surreal<Db> = Surreal::init();
+    let reconnects = AtomicUsize::new(0);
+    let count = with_auto_reconnect(
+        || async {
+            let mut response = db.query("RETURN 1").await?;
+            response.take::<Option<i64>>(0)
+        },
+        || async {
+            reconnects.fetch_add(1, AtomicOrdering::SeqCst);
+            db.connect::<Mem>(()).await?;
+            db.use_ns("test").use_db("test").await
+        },
+    )
+    .await?;
+    assert_eq!(count, Some(1));
+    assert_eq!(reconnects.load(AtomicOrdering::SeqCst), 1);
+
+    // Connected now, so no reconnect
+    let again = with_auto_reconnect(
+        || async { db.select::<Vec<Building>>("building_tbl").await },
+        || async { unreachable!("already connected") },
+    )
+    .await?;
+    assert!(again.is_empty());
+
+    Ok(())
+}
+
+fn test_thing_msgpack() -> Result<(), Box<dyn Error>> {
+    let things = [
+        Thing::from(("building_tbl", Id::Number(1234567890))),
+        Thing::from(("building_tbl", "1234567890")),
+        // `Id::uuid()` makes a string id holding a new UUID
+        Thing::from(("building_tbl", Id::uuid())),
+        parse_tbl_id("building_tbl:{ city: 'Springfield', number: 123 }")?,
+    ];
+    for thing in &things {
+        let bytes = thing_to_msgpack(thing);
+        let back = thing_from_msgpack(&bytes)?;
+        dbg!(bytes.len(), &back);
+        assert!(things_equal(&back, thing), "{back:?} != {thing:?}");
+    }
+    assert!(thing_from_msgpack(b"not msgpack").is_err());
+
+    Ok(())
+}
+
+async fn test_count_distinct() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    let table = "distinct_tbl";
+    let rows = [
+        ("1", "1 Dup St"),
+        ("2", "1 Dup St"),
+        ("3", "2 Dup St"),
+        ("4", "2 Dup St"),
+    ]
+    .map(|(id, address)| (id.to_owned(), address.to_owned()));
+    create_buildings(&db, table, &rows).await?;
+
+    assert_eq!(count_distinct(&db, table, "address").await?, 2);
+    assert_eq!(
+        count_distinct(&db, "distinct_empty_tbl", "address").await?,
+        0
+    );
+    assert!(count_distinct(&db, table, "address) FROM x; --")
+        .await
+        .is_err());
+
+    Ok(())
+}
+
+// Run with `cargo run --features chrono`
+#[cfg(feature = "chrono")]
+async fn test_get_id_as_datetime() -> Result<(), Box<dyn Error>> {
+    use chrono::TimeZone;
+
+    let db = connect_mem_named("test", "test").await?;
+    let created: Vec<BuildingWithThing> = db
+        .query("CREATE building_tbl:⟨2024-01-01T00:00:00Z⟩ SET address = '1 Time St'")
+        .await?
+        .take(0)?;
+    let thing = &created[0].id;
+    dbg!(thing);
+    let expected = chrono::Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    assert_eq!(thing.get_id_as_datetime(), Some(expected));
+    assert_eq!((&thing).get_id_as_datetime(), Some(expected));
+
+    let not_a_datetime = Thing::from(("building_tbl", "1234567890"));
+    assert_eq!(not_a_datetime.get_id_as_datetime(), None);
+
+    Ok(())
+}
+
+fn test_record_id_serde() -> Result<(), Box<dyn Error>> {
+    let record = Record {
+        id: Thing::from(("building_tbl", "1234567890")),
+        data: Building {
+            address: "123 Main St".to_owned(),
+        },
+    };
+    let json = serde_json::to_value(&record)?;
+    dbg!(&json);
+    assert_eq!(json["id"], "building_tbl:⟨1234567890⟩");
+    assert_eq!(json["data"]["address"], "123 Main St");
+
+    let back: Record<Building> = serde_json::from_value(json)?;
+    assert!(things_equal(&back.id, &record.id));
+    assert_eq!(back.data.address, record.data.address);
+
+    let numeric = RecordId(Thing::from(("building_tbl", Id::Number(1234567890))));
+    let json = serde_json::to_string(&numeric)?;
+    assert_eq!(json, r#""building_tbl:1234567890""#);
+    let back: RecordId = serde_json::from_str(&json)?;
+    assert_eq!(back, numeric);
+    assert!(serde_json::from_str::<RecordId>(r#""not a record id""#).is_err());
+
+    Ok(())
+}
+
+async fn test_select_as_of() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    let table = "as_of_tbl";
+    let rows = [("1".to_owned(), "1 Old St".to_owned())];
+    create_buildings(&db, table, &rows).await?;
+    let before = Datetime::default();
+    db.query("UPDATE as_of_tbl:⟨1⟩ SET address = '1 New St'")
+        .await?
+        .check()?;
+
+    // `Mem` would give "1 New St", not the row as it was at `before`
+    let result: Result<Vec<BuildingWithRidString>, _> = select_as_of(&db, table, before).await;
+    assert!(matches!(result, Err(RecordIdError::VersioningUnsupported)));
+
+    Ok(())
+}
+
+async fn test_select_addresses_in() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    let rows = [("1", "1 In St"), ("2", "2 In St"), ("3", "3 Out St")]
+        .map(|(id, address)| (id.to_owned(), address.to_owned()));
+    create_buildings(&db, "building_tbl", &rows).await?;
+
+    let mut found = select_addresses_in(&db, &["1 In St", "2 In St"]).await?;
+    found.sort_by(|a, b| cmp_things(&a.id, &b.id));
+    dbg!(&found);
+    let addresses: Vec<&str> = found.iter().map(|b| b.address.as_str()).collect();
+    assert_eq!(addresses, ["1 In St", "2 In St"]);
+
+    assert!(select_addresses_in(&db, &[]).await?.is_empty());
+
+    Ok(())
+}
+
+async fn test_next_seq() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    for expected in 1..=3 {
+        assert_eq!(next_seq(&db, "seq_test").await?, expected);
+    }
+
+    // Concurrent callers never get the same value
+    let tasks: Vec<_> = (0..10)
+        .map(|_| {
+            let db = db.clone();
+            tokio::spawn(async move {
+                next_seq(&db, "seq_concurrent")
+                    .await
+                    .map_err(|e| e.to_string())
+            })
+        })
+        .collect();
+    let mut values = Vec::new();
+    for task in tasks {
+        values.push(task.await??);
+    }
+    values.sort();
+    assert_eq!(values, (1..=10).collect::<Vec<i64>>());
+
+    let first = create_with_seq(&db, "seq_tbl", "1 Seq St").await?;
+    let second = create_with_seq(&db, "seq_tbl", "2 Seq St").await?;
+    assert_eq!(first.get_tbl_id(), "seq_tbl:1");
+    assert_eq!(second.get_tbl_id(), "seq_tbl:2");
+
+    Ok(())
+}
+
+async fn test_group_by_address() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    let rows = [
+        ("1", "1 Group St"),
+        ("2", "2 Group St"),
+        ("3", "1 Group St"),
+    ]
+    .map(|(id, address)| (id.to_owned(), address.to_owned()));
+    create_buildings(&db, "building_tbl", &rows).await?;
+
+    let groups = group_by_address(&db).await?;
+    dbg!(&groups);
+    let sizes: Vec<(&str, usize)> = groups.iter().map(|(a, b)| (a.as_str(), b.len())).collect();
+    assert_eq!(sizes, [("1 Group St", 2), ("2 Group St", 1)]);
+    assert!(groups["1 Group St"]
+        .iter()
+        .all(|b| b.address == "1 Group St"));
+
+    Ok(())
+}
+
+async fn test_derive_surreal_record(
+    db: &Surreal<Db>,
+    table: &str,
+    rid: &str,
+) -> Result<(), Box<dyn Error>> {
+    #[derive(SurrealRecord, Deserialize, Debug)]
+    struct DerivedBuilding {
+        id: Thing,
+        address: String,
+    }
+
+    // Naming the module that exports `IdTraits`, as another crate would
+    #[derive(SurrealRecord, Deserialize, Debug)]
+    #[surreal(crate = crate::prelude)]
+    struct PreludeBuilding {
+        id: surrealdb::sql::Thing,
+    }
+
+    let buildings: Vec<DerivedBuilding> = db.select(table).await?;
+    dbg!(&buildings);
+    let building = &buildings[0];
+    assert_eq!(building.tbl_id(), format!("{table}:⟨{rid}⟩"));
+    assert_eq!(building.rid(), rid);
+    assert_eq!(building.tbl(), table);
+    assert!(!building.address.is_empty());
+    let buildings: Vec<PreludeBuilding> = db.select(table).await?;
+    assert_eq!(buildings[0].tbl_id(), format!("{table}:⟨{rid}⟩"));
+
+    let mut response = bound_table(db, DerivedBuilding::rid_projection(), table).await?;
+    let projected: Vec<BuildingWithRidString> = response.take(0)?;
+    assert_eq!(projected[0].rid, rid);
+
+    Ok(())
+}
+
+async fn test_select_all_with_warnings() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    // Any name `bound_table` takes, escaped ones included
+    for table in ["warn_tbl", "warn tbl"] {
+        let _: Vec<BuildingWithThing> = db
+            .create(table)
+            .content(Building {
+                address: "1 Warn St".to_string(),
+            })
+            .await?;
+
+        let (rows, warnings): (Vec<BuildingWithThing>, _) =
+            select_all_with_warnings(&db, table).await?;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].id.get_tbl(), table);
+        // Nothing to report from SurrealDB 1.5
+        assert!(warnings.is_empty());
+    }
+
+    Ok(())
+}
+
+fn test_thing_cursor() -> Result<(), Box<dyn Error>> {
+    let things = [
+        Thing::from(("building_tbl", Id::Number(1234567890))),
+        Thing::from(("building_tbl", "1234567890")),
+        Thing::from(("building_tbl", "a/b?c=d&e")),
+        parse_tbl_id("building_tbl:[1, 'two']")?,
+    ];
+    for thing in &things {
+        let cursor = thing_to_cursor(thing);
+        dbg!(&cursor);
+        assert!(cursor
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'));
+        let back = thing_from_cursor(&cursor)?;
+        assert!(things_equal(&back, thing), "{back:?} != {thing:?}");
+    }
+
+    // Not base64, and base64 of something that isn't a `Thing`
+    for bad in ["not a cursor!", "bm90IG1zZ3BhY2s"] {
+        assert!(matches!(
+            thing_from_cursor(bad),
+            Err(RecordIdError::Parse(_))
+        ));
+    }
+
+    Ok(())
+}
+
+async fn test_serde_surreal_duration() -> Result<(), Box<dyn Error>> {
+    #[derive(Serialize, Deserialize, Debug)]
+    struct LeasedBuilding {
+        address: String,
+        #[serde(with = "serde_surreal_duration")]
+        lease_term: Duration,
+    }
+
+    let db = connect_mem_named("test", "test").await?;
+    let week = Duration::from_secs(7 * 24 * 60 * 60);
+    db.query("CREATE lease_tbl:a SET address = '1 Lease St', lease_term = 1w")
+        .await?
+        .check()?;
+    let leased: Option<LeasedBuilding> = db.select(("lease_tbl", "a")).await?;
+    dbg!(&leased);
+    assert_eq!(leased.map(|b| b.lease_term), Some(week));
+
+    // Written back as a duration, not serde's `{ secs, nanos }`
+    let _: Option<LeasedBuilding> = db
+        .create(("lease_tbl", "b"))
+        .content(LeasedBuilding {
+            address: "2 Lease St".to_string(),
+            lease_term: week,
+        })
+        .await?;
+    let mut response = db
+        .query("SELECT VALUE type::is::duration(lease_term) FROM lease_tbl:b")
+        .await?;
+    let is_duration: Option<bool> = response.take(0)?;
+    assert_eq!(is_duration, Some(true));
+    let leased: Option<LeasedBuilding> = db.select(("lease_tbl", "b")).await?;
+    assert_eq!(leased.map(|b| b.lease_term), Some(week));
+
+    Ok(())
+}
+
+async fn test_prepared_query() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    let rows = [("1", "1 Prep St"), ("2", "2 Prep St"), ("3", "1 Prep St")]
+        .map(|(id, address)| (id.to_owned(), address.to_owned()));
+    create_buildings(&db, "prep_tbl", &rows).await?;
+
+    let by_address = PreparedQuery::new(
+        "SELECT meta::id(id) AS rid, address FROM prep_tbl WHERE address = $a ORDER BY rid",
+    );
+    let first: Vec<BuildingWithRidString> = by_address.run(&db, ("a", "1 Prep St")).await?;
+    dbg!(&first);
+    let rids: Vec<&str> = first.iter().map(|b| b.rid.as_str()).collect();
+    assert_eq!(rids, ["1", "3"]);
+
+    let second: Vec<BuildingWithRidString> = by_address.run(&db, ("a", "2 Prep St")).await?;
+    assert_eq!(second.len(), 1);
+    assert_eq!(second[0].rid, "2");
+
+    Ok(())
+}
+
+async fn test_select_all_bounded() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    let rows: Vec<(String, String)> = (1..=5)
+        .map(|n| (n.to_string(), format!("{n} Bound St")))
+        .collect();
+    create_buildings(&db, "bounded_tbl", &rows).await?;
+
+    let result: Result<Vec<Building>, _> = select_all_bounded(&db, "bounded_tbl", 3).await;
+    let err = result.expect_err("5 rows is over the limit of 3");
+    println!("{err}");
+    assert!(matches!(
+        err,
+        RecordIdError::TooManyRows { count: 5, limit: 3 }
+    ));
+
+    let buildings: Vec<Building> = select_all_bounded(&db, "bounded_tbl", 5).await?;
+    assert_eq!(buildings.len(), 5);
+    let buildings: Vec<Building> = select_all_bounded(&db, "empty_bounded_tbl", 0).await?;
+    assert!(buildings.is_empty());
+
+    Ok(())
+}
+
+async fn test_get_type_thing_expr(
+    db: &Surreal<Db>,
+    table: &str,
+    rid: &str,
+) -> Result<(), Box<dyn Error>> {
+    let numeric = Thing::from(("building_tbl", Id::Number(1234567890)));
+    assert_eq!(
+        numeric.get_type_thing_expr(),
+        "type::thing('building_tbl', 1234567890)"
+    );
+    let string = Thing::from(("building_tbl", "1234567890"));
+    assert_eq!(
+        string.get_type_thing_expr(),
+        "type::thing('building_tbl', '1234567890')"
+    );
+    let quote = Thing::from(("building_tbl", "it's"));
+    dbg!(quote.get_type_thing_expr());
+
+    // Each resolves back to the same Thing, the quote included
+    let array = parse_tbl_id("building_tbl:[1, 'two']")?;
+    for thing in [&numeric, &string, &quote, &array] {
+        let mut response = db
+            .query(format!("RETURN {}", thing.get_type_thing_expr()))
+            .await?;
+        let back: Option<Thing> = response.take(0)?;
+        assert_eq!(back.as_ref(), Some(thing));
+    }
+
+    // And selects the record main created
+    let thing = Thing::from((table, rid));
+    let mut response = db
+        .query(format!("SELECT * FROM {}", thing.get_type_thing_expr()))
+        .await?;
+    let buildings: Vec<BuildingWithThing> = response.take(0)?;
+    assert_eq!(buildings.len(), 1);
+    assert_eq!(buildings[0].id, thing);
+
+    Ok(())
+}
+
+async fn test_building_with_parent() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    let parent = Thing::from(("parent_tbl", "campus"));
+    let child = Thing::from(("parent_tbl", "annex"));
+    db.query("CREATE $parent SET address = '1 Campus Dr'")
+        .query("CREATE $child SET address = '2 Campus Dr', parent = $parent")
+        .bind(("parent", &parent))
+        .bind(("child", &child))
+        .await?
+        .check()?;
+
+    let selected: Option<BuildingWithParent> = db.select(("parent_tbl", "annex")).await?;
+    dbg!(&selected);
+    let selected = selected.expect("child was created");
+    assert_eq!(selected.id, child);
+    assert_eq!(selected.parent.as_ref(), Some(&parent));
+    assert_eq!(
+        selected.parent.as_ref().map(|p| p.get_tbl_id()),
+        Some("parent_tbl:campus".to_owned())
+    );
+
+    // The parent has no link, and the query path reads it the same way
+    let rows: Vec<BuildingWithParent> = select_where(
+        &db,
+        "parent_tbl",
+        "address = $a",
+        vec![("a", "1 Campus Dr".into())],
+    )
+    .await?;
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].id, parent);
+    assert!(rows[0].parent.is_none());
+    let rows: Vec<BuildingWithParent> = select_where(
+        &db,
+        "parent_tbl",
+        "address = $a",
+        vec![("a", "2 Campus Dr".into())],
+    )
+    .await?;
+    assert_eq!(rows[0].parent.as_ref(), Some(&parent));
+
+    Ok(())
+}
+
+// Run with `cargo run --features ws`, needs the `surreal` server binary
+// on the PATH. It's started half a second after the wait begins.
+#[cfg(feature = "ws")]
+async fn test_wait_until_ready() -> Result<(), Box<dyn Error>> {
+    let url = "127.0.0.1:8765";
+
+    // Nothing listening
+    let err = match wait_until_ready(url, Duration::from_millis(300)).await {
+        Ok(_) => return Err("connected without a server".into()),
+        Err(e) => e,
+    };
+    assert!(matches!(
+        err.downcast_ref::<RecordIdError>(),
+        Some(RecordIdError::Timeout(_))
+    ));
+
+    let server = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        std::process::Command::new("surreal")
+            .args(["start", "--bind", url, "--log", "none", "memory"])
+            .spawn()
+    });
+    let started = Instant::now();
+    let result = wait_until_ready(url, Duration::from_secs(10)).await;
+    let mut child = server.await??;
+    child.kill()?;
+    child.wait()?;
+    let db = result?;
+    dbg!(started.elapsed());
+    assert!(started.elapsed() >= Duration::from_millis(500));
+    db.health().await?;
+
+    Ok(())
+}
+
+async fn test_select_all_parallel(db: &Surreal<Db>, table: &str) -> Result<(), Box<dyn Error>> {
+    let mut serial = select_all_flat(db, table).await?;
+    let mut parallel = select_all_parallel(db, table).await?;
+    dbg!(&parallel);
+    assert!(!parallel.is_empty());
+    serial.sort_by(|a, b| a.rid.cmp(&b.rid));
+    parallel.sort_by(|a, b| a.rid.cmp(&b.rid));
+    assert_eq!(parallel.len(), serial.len());
+    for (p, s) in parallel.iter().zip(&serial) {
+        assert_eq!((&p.rid, &p.address), (&s.rid, &s.address));
+    }
+
+    Ok(())
+}
+
+fn test_thing_via_sql_parse() -> Result<(), Box<dyn Error>> {
+    let numeric = thing_via_sql_parse("building_tbl:1234567890")?;
+    assert_eq!(numeric.id, Id::Number(1234567890));
+    let escaped = thing_via_sql_parse("building_tbl:⟨1234567890⟩")?;
+    assert_eq!(escaped.id, Id::String("1234567890".to_owned()));
+    let plain = thing_via_sql_parse("building_tbl:abc")?;
+    assert_eq!(plain.id, Id::String("abc".to_owned()));
+    assert_eq!(thing_via_sql_parse("r'building_tbl:abc'")?, plain);
+
+    // `get_tbl_id()` output is accepted, including the backtick form
+    for id in ["1234567890", "a b", "a⟩b", "it's"] {
+        let thing = Thing::from(("building_tbl", id));
+        let back = thing_via_sql_parse(&thing.get_tbl_id())?;
+        assert!(things_equal(&back, &thing), "{back:?} != {thing:?}");
+    }
+
+    for bad in [
+        "1234567890",
+        "building_tbl",
+        " building_tbl:abc",
+        "t:⟨a\\⟩b⟩",
+    ] {
+        assert!(
+            matches!(thing_via_sql_parse(bad), Err(RecordIdError::Parse(_))),
+            "{bad:?} parsed"
+        );
+    }
+
+    Ok(())
+}
+
+async fn test_select_with_meta(
+    db: &Surreal<Db>,
+    table: &str,
+    rid: &str,
+) -> Result<(), Box<dyn Error>> {
+    let metas = select_with_meta(db, table).await?;
+    dbg!(&metas);
+    assert_eq!(
+        metas,
+        [RecordMeta {
+            rid: rid.to_owned(),
+            tbl: table.to_owned(),
+        }]
+    );
+
+    Ok(())
+}
+
+async fn test_upsert_building() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+
+    let (building, action) = upsert_building(&db, "upsert_tbl", "1", "1 Upsert St").await?;
+    dbg!(&building, action);
+    assert_eq!(action, UpsertAction::Created);
+    assert_eq!(building.id, Thing::from(("upsert_tbl", "1")));
+    assert_eq!(building.address, "1 Upsert St");
+
+    let (building, action) = upsert_building(&db, "upsert_tbl", "1", "2 Upsert St").await?;
+    assert_eq!(action, UpsertAction::Updated);
+    assert_eq!(building.address, "2 Upsert St");
+    assert_eq!(query_count(&db, "upsert_tbl").await?, 1);
+
+    Ok(())
+}
+
+async fn test_building_addr_alias() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    db.query("CREATE legacy_tbl:old SET addr = '1 Legacy St'")
+        .query("CREATE legacy_tbl:new SET address = '2 Legacy St'")
+        .await?
+        .check()?;
+
+    let old: Option<Building> = db.select(("legacy_tbl", "old")).await?;
+    dbg!(&old);
+    assert_eq!(old.map(|b| b.address).as_deref(), Some("1 Legacy St"));
+
+    // The query helpers read both spellings too
+    let mut buildings: Vec<Building> = Vec::new();
+    select_all_into(&db, "legacy_tbl", &mut buildings).await?;
+    let mut addresses: Vec<&str> = buildings.iter().map(|b| b.address.as_str()).collect();
+    addresses.sort();
+    assert_eq!(addresses, ["1 Legacy St", "2 Legacy St"]);
+
+    // Written back it's always `address`
+    let _: Vec<BuildingWithThing> = db
+        .create("legacy_tbl")
+        .content(Building {
+            address: "3 Legacy St".to_string(),
+        })
+        .await?;
+    let mut response = db
+        .query("SELECT VALUE address FROM legacy_tbl WHERE addr = NONE")
+        .await?;
+    let written: Vec<String> = response.take(0)?;
+    assert_eq!(written.len(), 2);
+
+    Ok(())
+}
+
+async fn test_things_equal_ci() -> Result<(), Box<dyn Error>> {
+    let upper = parse_tbl_id("Building_Tbl:1")?;
+    let lower = parse_tbl_id("building_tbl:1")?;
+    assert!(things_equal_ci(&upper, &lower));
+    assert!(!things_equal(&upper, &lower));
+    // Ids stay exact
+    let upper_id = parse_tbl_id("building_tbl:ABC")?;
+    let lower_id = parse_tbl_id("building_tbl:abc")?;
+    assert!(!things_equal_ci(&upper_id, &lower_id));
+    assert!(!things_equal_ci(&lower, &parse_tbl_id("building_tbl:⟨1⟩")?));
+
+    // Which is why the strict check is the default, they're two records
+    let db = connect_mem_named("test", "test").await?;
+    db.query("CREATE Building_Tbl:1 SET address = '1 Upper St'")
+        .query("CREATE building_tbl:1 SET address = '1 Lower St'")
+        .await?
+        .check()?;
+    let upper_row: Option<Building> = db.select(("Building_Tbl", 1)).await?;
+    let lower_row: Option<Building> = db.select(("building_tbl", 1)).await?;
+    assert_eq!(upper_row.map(|b| b.address).as_deref(), Some("1 Upper St"));
+    assert_eq!(lower_row.map(|b| b.address).as_deref(), Some("1 Lower St"));
+
+    Ok(())
+}
+
+async fn test_select_all_chunked() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    let rows: Vec<(String, String)> = (0..10)
+        .map(|n| (format!("{n:02}"), format!("{n} Chunk St")))
+        .collect();
+    create_buildings(&db, "chunk_tbl", &rows).await?;
+
+    let chunks: Vec<Vec<BuildingWithRidString>> = select_all_chunked(&db, "chunk_tbl", 3).await?;
+    let sizes: Vec<usize> = chunks.iter().map(Vec::len).collect();
+    assert_eq!(sizes, [3, 3, 3, 1]);
+    // In id order with nothing repeated or skipped
+    let rids: Vec<&str> = chunks.iter().flatten().map(|b| b.rid.as_str()).collect();
+    let expected: Vec<&str> = rows.iter().map(|(id, _)| id.as_str()).collect();
+    assert_eq!(rids, expected);
+
+    let chunks: Vec<Vec<BuildingWithRidString>> = select_all_chunked(&db, "chunk_tbl", 5).await?;
+    let sizes: Vec<usize> = chunks.iter().map(Vec::len).collect();
+    assert_eq!(sizes, [5, 5]);
+    let chunks: Vec<Vec<Building>> = select_all_chunked(&db, "empty_chunk_tbl", 3).await?;
+    assert!(chunks.is_empty());
+    assert!(select_all_chunked::<Building>(&db, "chunk_tbl", 0)
+        .await
+        .is_err());
+
+    Ok(())
+}
+
+fn test_annotate_escapes() -> Result<(), Box<dyn Error>> {
+    let thing = parse_tbl_id("building_tbl:⟨1234567890⟩")?;
+    let annotated = annotate_escapes(&thing.get_tbl_id());
+    println!("{annotated}");
+    assert_eq!(annotated, "building_tbl:[U+27E8]1234567890[U+27E9]");
+    assert_eq!(annotate_escapes("building_tbl:<1>"), "building_tbl:<1>");
+    assert_eq!(annotate_escapes("building_tbl:abc"), "building_tbl:abc");
+
+    Ok(())
+}
+
+async fn test_select_by_id_or_thing(
+    db: &Surreal<Db>,
+    table: &str,
+    rid: &str,
+) -> Result<(), Box<dyn Error>> {
+    let tbl_id = Thing::from((table, rid)).get_tbl_id();
+
+    let (thing, building) = select_by_id_or_thing::<BuildingWithThing>(db, &tbl_id).await?;
+    assert_eq!(thing, Thing::from((table, rid)));
+    assert_eq!(building.map(|b| b.id), Some(thing));
+
+    // A stored record has no `rid`, so the row is None but the Thing is
+    // still returned
+    let (thing, building) = select_by_id_or_thing::<BuildingWithRidString>(db, &tbl_id).await?;
+    dbg!(&thing, &building);
+    assert_eq!(thing, Thing::from((table, rid)));
+    assert!(building.is_none());
+
+    let missing = format!("{table}:no_such_record");
+    match select_by_id_or_thing::<BuildingWithThing>(db, &missing).await {
+        Err(RecordIdError::NotFound(thing)) => assert_eq!(thing.get_tbl_id(), missing),
+        r => return Err(format!("unexpected result {r:?}").into()),
+    }
+
+    Ok(())
+}
+
+async fn test_select_by_thing_bound() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    db.query("CREATE bound_thing_tbl:1 SET address = '1 Number St'")
+        .query("CREATE bound_thing_tbl:⟨1⟩ SET address = '1 String St'")
+        .await?
+        .check()?;
+
+    let numeric = Thing::from(("bound_thing_tbl", Id::Number(1)));
+    let found = select_by_thing_bound(&db, &numeric)
+        .await?
+        .expect("created above");
+    dbg!(&found);
+    assert!(found.id.is_numeric_id());
+    assert_eq!(found.address, "1 Number St");
+
+    let string = Thing::from(("bound_thing_tbl", "1"));
+    let found = select_by_thing_bound(&db, &string)
+        .await?
+        .expect("created above");
+    assert!(!found.id.is_numeric_id());
+    assert_eq!(found.address, "1 String St");
+
+    let missing = Thing::from(("bound_thing_tbl", Id::Number(2)));
+    assert!(select_by_thing_bound(&db, &missing).await?.is_none());
+
+    Ok(())
+}
+
+async fn test_select_all_strict() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    let rows = [("1".to_owned(), "1 Strict St".to_owned())];
+    create_buildings(&db, "strict_tbl", &rows).await?;
+    ensure_building_schema(&db, "defined_tbl").await?;
+    assert_eq!(list_tables(&db).await?, ["defined_tbl", "strict_tbl"]);
+
+    let buildings: Vec<Building> = select_all_strict(&db, "strict_tbl").await?;
+    assert_eq!(buildings.len(), 1);
+    // Defined but empty is fine
+    let buildings: Vec<Building> = select_all_strict(&db, "defined_tbl").await?;
+    assert!(buildings.is_empty());
+
+    let result: Result<Vec<Building>, _> = select_all_strict(&db, "stirct_tbl").await;
+    let err = result.expect_err("misspelled table");
+    println!("{err}");
+    match err {
+        RecordIdError::UnknownTable { table } => assert_eq!(table, "stirct_tbl"),
+        e => return Err(format!("unexpected error {e:?}").into()),
+    }
+    // Where plain `db.select` doesn't notice
+    let buildings: Vec<Building> = db.select("stirct_tbl").await?;
+    assert!(buildings.is_empty());
+
+    Ok(())
+}
+
+fn test_assert_thing_id() -> Result<(), Box<dyn Error>> {
+    let string = Thing::from(("building_tbl", "1234567890"));
+    let numeric = Thing::from(("building_tbl", Id::Number(1234567890)));
+    assert_thing_id!(string, ("building_tbl", "1234567890"));
+    assert_thing_id!(&numeric, ("building_tbl", "1234567890"));
+
+    // A failure names both forms of both sides. The default hook would
+    // print the panic too, so it's swapped out while this runs.
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let result = std::panic::catch_unwind(|| {
+        assert_thing_id!(string, ("building_tbl", "123"));
+    });
+    std::panic::set_hook(hook);
+    let payload = result.expect_err("ids differ");
+    let msg = payload
+        .downcast_ref::<String>()
+        .ok_or("panic payload isn't a String")?;
+    println!("{msg}");
+    assert!(msg.contains("building_tbl:⟨1234567890⟩"));
+    assert!(msg.contains("unescaped building_tbl:1234567890"));
+    assert!(msg.contains("building_tbl:⟨123⟩"));
+    assert!(msg.contains("unescaped building_tbl:123"));
+
+    Ok(())
+}
+
+async fn test_stream_all_resumable() -> Result<(), Box<dyn Error>> {
+    let db = connect_mem_named("test", "test").await?;
+    let rows: Vec<(String, String)> = (0..10)
+        .map(|n| (format!("{n:02}"), format!("{n} Stream St")))
+        .collect();
+    create_buildings(&db, "resumable_tbl", &rows).await?;
+
+    // Nothing to reconnect in memory, every row once and in order
+    let reconnects = AtomicUsize::new(0);
+    let stream = stream_all_resumable(&db, "resumable_tbl", 3, 3, || {
+        reconnects.fetch_add(1, AtomicOrdering::Relaxed);
+        future::ready(Ok(()))
+    });
+    let streamed: Vec<Result<BuildingWithThing, RecordIdError>> = stream.collect().await;
+    let streamed: Vec<BuildingWithThing> = streamed.into_iter().collect::<Result<_, _>>()?;
+    let ids: Vec<String> = streamed.iter().map(|b| b.id.get_id()).collect();
+    let expected: Vec<String> = rows.iter().map(|(id, _)| id.clone()).collect();
+    assert_eq!(ids, expected);
+    assert_eq!(reconnects.load(AtomicOrdering::Relaxed), 0);
+
+    // A handle that isn't connected yet connects on the first failure
+    let db = Surreal::<Db>::init();
+    let stream = stream_all_resumable(&db, "resumable_tbl", 3, 2, || async {
+        db.connect::<Mem>(()).await?;
+        db.use_ns("test").use_db("test").await?;
+        db.query("CREATE resumable_tbl:1 SET address = '1 Reconnect St'")
+            .await?
+            .check()?;
+        Ok(())
+    });
+    let streamed: Vec<Result<BuildingWithThing, RecordIdError>> = stream.collect().await;
+    assert_eq!(streamed.len(), 1);
+    assert_eq!(
+        streamed[0].as_ref().ok().map(|b| b.address.as_str()),
+        Some("1 Reconnect St")
+    );
+
+    Ok(())
+}
+
+// Run with `cargo run --features ws`, needs the `surreal` server binary
+// on the PATH. The server is killed after the first page and restarted on
+// the same files, the stream has to pick up where it left off.
+#[cfg(feature = "ws")]
+async fn test_stream_all_resumable_ws() -> Result<(), Box<dyn Error>> {
+    use surrealdb::opt::auth::Root;
+
+    let url = "127.0.0.1:8766";
+    let path = std::env::temp_dir().join(format!("resumable-{}", std::process::id()));
+    let start_server = || {
+        std::process::Command::new("surreal")
+            .args(["start", "--bind", url, "--log", "none", "--user", "root"])
+            .args(["--pass", "root"])
+            .arg(format!("file://{}", path.display()))
+            .spawn()
+    };
+    let connect = |db: Surreal<surrealdb::engine::remote::ws::Client>| async move {
+        db.signin(Root {
+            username: "root",
+            password: "root",
+        })
+        .await?;
+        db.use_ns("test").use_db("test").await?;
+        Ok::<_, surrealdb::Error>(db)
+    };
+
+    let mut server = start_server()?;
+    let db = connect(wait_until_ready(url, Duration::from_secs(10)).await?).await?;
+    let rows: Vec<(String, String)> = (0..10)
+        .map(|n| (format!("{n:02}"), format!("{n} Remote St")))
+        .collect();
+    for (id, address) in &rows {
+        db.query("CREATE type::thing('resumable_tbl', $id) SET address = $address")
+            .bind(("id", id))
+            .bind(("address", address))
+            .await?
+            .check()?;
+    }
+
+    let mut restarted = None;
+    let mut stream = Box::pin(stream_all_resumable(&db, "resumable_tbl", 4, 50, || {
+        // Ws reconnects the socket on its own, the session has to be redone
+        let db = db.clone();
+        async move { connect(db).await.map(drop) }
+    }));
+    let mut ids = Vec::new();
+    while let Some(building) = stream.next().await {
+        ids.push(building?.id.get_id());
+        if ids.len() == 4 {
+            // The first page is in, drop the connection under the stream
+            server.kill()?;
+            server.wait()?;
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            restarted = Some(start_server()?);
+        }
+    }
+    drop(stream);
+    if let Some(mut server) = restarted {
+        server.kill()?;
+        server.wait()?;
+    }
+    let _ = std::fs::remove_dir_all(&path);
+
+    let expected: Vec<String> = rows.iter().map(|(id, _)| id.clone()).collect();
+    assert_eq!(ids, expected);
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let config = config_from_env();
+    dbg!(&config);
+
+    // Create a new SurrealDB instance, the helpers all take a local
+    // `Surreal<Db>` so only the in-memory engine can be used for now.
+    let db = Surreal::new::<Mem>(()).await?;
+    dbg!(&db);
+
+    db.use_ns(&config.ns).use_db(&config.db).await?;
 
     // Add a record with a `rid` and `address` fields
     let table = "building_tbl";
@@ -180,6 +5456,115 @@ async fn main() -> Result<(), Box<dyn Error>> {
     test_select(&db, address).await?;
     test_query(&db, address, rid).await?;
     test_select_thing_with_id_traits(&db, address, table, rid).await?;
+    test_select_all_flat(&db, address, table, rid).await?;
+    test_select_fill_rid(&db, address, table, rid).await?;
+    prelude_test::test_prelude().await?;
+    test_select_all_timeout(&db, address, table).await?;
+    test_get_tbl_id_tuple()?;
+    test_insert_or_ignore(&db, address).await?;
+    #[cfg(feature = "proptest")]
+    test_thing_round_trip_prop()?;
+    test_query_count(&db, table).await?;
+    test_batch_10k(&db).await?;
+    test_sortable_thing()?;
+    test_config_from_env()?;
+    test_select_with_owner(&db).await?;
+    #[cfg(feature = "schemars")]
+    test_json_schema()?;
+    test_raw_query(&db, address, table).await?;
+    test_split_tbl_id()?;
+    test_live_buildings_filtered().await?;
+    test_export_csv(&db).await?;
+    test_id_from_parts()?;
+    test_cached_store().await?;
+    test_get_id_range()?;
+    test_record_id_display()?;
+    test_try_select(&db, address, table, rid).await?;
+    test_expect_table(&db, address, table, rid).await?;
+    test_ping(&db).await?;
+    test_select_records_tuple(&db, address, table, rid).await?;
+    test_next_numeric_id()?;
+    test_select_all_json(&db, address, table, rid).await?;
+    test_change_id(&db, address).await?;
+    test_id_traits_for_ref()?;
+    test_get_id_as_numbers()?;
+    test_bound_table(&db, table).await?;
+    test_proto_record_id()?;
+    test_soft_delete(&db).await?;
+    test_soft_delete_schemafull().await?;
+    test_query_expect_slots(&db, address, table).await?;
+    test_seed_addresses().await?;
+    test_explain_rid_behavior(&db, table, rid).await?;
+    test_get_id_cow()?;
+    test_select_all_ordered(&db).await?;
+    #[cfg(feature = "tracing")]
+    test_record_thing()?;
+    test_count_by_address().await?;
+    test_get_tbl_id_with()?;
+    test_select_with_row_context().await?;
+    test_connect_mem_named().await?;
+    test_thing_path_segment()?;
+    test_select_all_retry(&db, table).await?;
+    test_compare_thing_formats()?;
+    test_select_first(&db, table, rid).await?;
+    test_value_id_traits(&db, table, rid).await?;
+    test_bulk_upsert().await?;
+    test_select_where(&db, table, address).await?;
+    test_things_equal_object_ids()?;
+    test_select_all_things().await?;
+    #[cfg(feature = "ulid")]
+    test_ulid_things().await?;
+    test_select_all_into().await?;
+    test_thing_heap_size()?;
+    test_select_is_main_street().await?;
+    test_drain_and_drop().await?;
+    test_select_all_fetch().await?;
+    test_get_tbl_id_idempotent()?;
+    test_select_all_arc(&db, table, rid).await?;
+    test_create_building_coerce().await?;
+    test_select_tagged_enum().await?;
+    test_thing_at_path()?;
+    test_select_keyset().await?;
+    test_thing_checked()?;
+    test_select_pairs(&db, table, rid, address).await?;
+    test_write_tbl_id()?;
+    test_select_all_consistent().await?;
+    test_clone_thing(&db, table, rid).await?;
+    test_with_auto_reconnect().await?;
+    test_thing_msgpack()?;
+    test_count_distinct().await?;
+    #[cfg(feature = "chrono")]
+    test_get_id_as_datetime().await?;
+    test_record_id_serde()?;
+    test_select_as_of().await?;
+    test_select_addresses_in().await?;
+    test_next_seq().await?;
+    test_group_by_address().await?;
+    test_derive_surreal_record(&db, table, rid).await?;
+    test_select_all_with_warnings().await?;
+    test_thing_cursor()?;
+    test_serde_surreal_duration().await?;
+    test_prepared_query().await?;
+    test_select_all_bounded().await?;
+    test_get_type_thing_expr(&db, table, rid).await?;
+    test_building_with_parent().await?;
+    #[cfg(feature = "ws")]
+    test_wait_until_ready().await?;
+    test_select_all_parallel(&db, table).await?;
+    test_thing_via_sql_parse()?;
+    test_select_with_meta(&db, table, rid).await?;
+    test_upsert_building().await?;
+    test_building_addr_alias().await?;
+    test_things_equal_ci().await?;
+    test_select_all_chunked().await?;
+    test_annotate_escapes()?;
+    test_select_by_id_or_thing(&db, table, rid).await?;
+    test_select_by_thing_bound().await?;
+    test_select_all_strict().await?;
+    test_assert_thing_id()?;
+    test_stream_all_resumable().await?;
+    #[cfg(feature = "ws")]
+    test_stream_all_resumable_ws().await?;
 
     Ok(())
 }
@@ -0,0 +1,56 @@
+// Formatting of `tbl:id` strings for `IdTraits::get_tbl_id`, kept free of
+// the rest of the crate so `benches/tbl_id.rs` can include it directly.
+use std::fmt::Write;
+use surrealdb::sql::{Id, Thing};
+
+// Whether SurrealDB's `escape_rid` (which isn't public) would wrap `s` in
+// `⟨` and `⟩`: it contains anything but ASCII alphanumerics and `_`, or is
+// all digits so it would otherwise parse as a number.
+pub fn needs_escape(s: &str) -> bool {
+    s.bytes().any(|b| !(b.is_ascii_alphanumeric() || b == b'_'))
+        || s.bytes().all(|b| b.is_ascii_digit())
+}
+
+// Append one `tbl` or `id` part, escaped as `write_tbl_id_with` would
+pub fn write_part(buf: &mut String, raw: &str, open: &str, close: &str) {
+    if !needs_escape(raw) {
+        buf.push_str(raw);
+    } else if close == "⟩" && raw.contains('⟩') {
+        // SurrealDB writes this `⟩` as `\⟩` but can't parse that back,
+        // backticks it can
+        buf.push('`');
+        for c in raw.chars() {
+            if c == '\\' || c == '`' {
+                buf.push('\\');
+            }
+            buf.push(c);
+        }
+        buf.push('`');
+    } else {
+        buf.push_str(open);
+        buf.push_str(raw);
+        buf.push_str(close);
+    }
+}
+
+// Append `thing` as `tbl{sep}id` to `buf`, with `open` and `close` around
+// the parts SurrealDB escapes. Numbers are written straight into `buf`.
+pub fn write_tbl_id_with(buf: &mut String, thing: &Thing, open: &str, close: &str, sep: &str) {
+    write_part(buf, &thing.tb, open, close);
+    buf.push_str(sep);
+    match &thing.id {
+        Id::Number(n) => {
+            let _ = write!(buf, "{n}");
+        }
+        Id::String(raw) => write_part(buf, raw, open, close),
+        id => {
+            let _ = write!(buf, "{id}");
+        }
+    }
+}
+
+// Append `thing.get_tbl_id()` to `buf`, so a loop formatting many Things
+// can reuse one buffer.
+pub fn write_tbl_id(buf: &mut String, thing: &Thing) {
+    write_tbl_id_with(buf, thing, "⟨", "⟩", ":");
+}